@@ -1,6 +1,6 @@
 use std::io::Read;
 mod transaction;
-use self::transaction::{Amount, Input, Output, Transaction};
+use self::transaction::{Amount, Input, Output, Transaction, Txid};
 
 fn read_u32(transaction_bytes: &mut &[u8]) -> u32 {
     let mut buffer = [0; 4];
@@ -40,11 +40,10 @@ fn read_compact_size(transaction_bytes: &mut &[u8]) -> u64 {
     }
 }
 
-fn read_txid(transaction_bytes: &mut &[u8]) -> String {
+fn read_txid(transaction_bytes: &mut &[u8]) -> Txid {
     let mut buffer = [0; 32];
     transaction_bytes.read(&mut buffer).unwrap();
-    buffer.reverse(); // txids are formatted in big endian
-    hex::encode(buffer)
+    Txid::from_bytes(buffer)
 }
 
 fn read_script(transaction_bytes: &mut &[u8]) -> String {
@@ -103,7 +102,7 @@ fn main() {
 
 #[cfg(test)]
 mod unit_tests {
-    use super::read_compact_size;
+    use super::{read_compact_size, read_txid};
 
     #[test]
     fn test_reading_compact_size() {
@@ -133,4 +132,16 @@ mod unit_tests {
         let expected_length = 20_000_u64;
         assert_eq!(length, expected_length);
     }
+
+    #[test]
+    fn test_txid_byte_order() {
+        // the first input of the transaction in main spends this txid, which is
+        // serialized in the reverse of the order explorers display it in
+        let serialized = hex::decode("42d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380").unwrap();
+        let txid = read_txid(&mut serialized.as_slice());
+        assert_eq!(
+            serde_json::to_string(&txid).unwrap(),
+            "\"8073cdf947ac97c23b77b055217da78d3ad71d30e1f6c095be8b30f7d6c1d542\""
+        );
+    }
 }
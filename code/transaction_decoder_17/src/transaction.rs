@@ -7,9 +7,27 @@ pub struct Transaction {
     pub outputs: Vec<Output>,
 }
 
+#[derive(Debug)]
+pub struct Txid([u8; 32]);
+
+impl Txid {
+    pub fn from_bytes(bytes: [u8; 32]) -> Txid {
+        Txid(bytes)
+    }
+}
+
+// txids are stored in the order they're serialized but displayed reversed
+impl Serialize for Txid {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = self.0;
+        bytes.reverse();
+        s.serialize_str(&hex::encode(bytes))
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct Input {
-    pub txid: String,
+    pub txid: Txid,
     pub output_index: u32,
     pub script: String,
     pub sequence: u32,
@@ -1,3 +1,4 @@
+use std::fmt;
 use serde::{Serialize, Serializer};
 
 #[derive(Debug, Serialize)]
@@ -20,7 +21,7 @@ impl Txid {
 
 impl Serialize for Txid {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let mut bytes = self.0.clone();
+        let mut bytes = self.0;
         bytes.reverse();
         s.serialize_str(&hex::encode(bytes))
     }
@@ -63,3 +64,19 @@ pub struct Output {
 fn as_btc<T: BitcoinValue, S: Serializer>(t: &T, s: S) -> Result<S::Ok, S::Error> {
     s.serialize_f64(t.to_btc())
 }
+
+#[derive(Debug)]
+pub enum Error {
+    // the input ended partway through a field
+    Truncated { field: &'static str },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Truncated { field } => write!(f, "transaction ended while reading {}", field),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
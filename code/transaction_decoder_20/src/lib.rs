@@ -3,66 +3,60 @@ mod transaction;
 use self::transaction::{Amount, Input, Output, Transaction, Txid};
 use sha2::{Digest, Sha256};
 use std::error::Error;
-use std::io::{Error as IOError};
-use clap::{arg, value_parser, Command};
+use std::io::{Error as IOError, ErrorKind};
+use serde::Serialize;
+use clap::{arg, value_parser, ArgAction, Command};
 
-fn read_u32(transaction_bytes: &mut &[u8]) -> Result<u32, IOError> {
+fn read_u32<R: Read>(transaction_bytes: &mut R) -> Result<u32, IOError> {
     let mut buffer = [0; 4];
-    transaction_bytes.read(&mut buffer)?;
+    transaction_bytes.read_exact(&mut buffer)?;
 
     Ok(u32::from_le_bytes(buffer))
 }
 
-fn read_amount(transaction_bytes: &mut &[u8]) -> Result<Amount, IOError> {
+fn read_amount<R: Read>(transaction_bytes: &mut R) -> Result<Amount, IOError> {
     let mut buffer = [0; 8];
-    transaction_bytes.read(&mut buffer)?;
+    transaction_bytes.read_exact(&mut buffer)?;
 
     Ok(Amount::from_sat(u64::from_le_bytes(buffer)))
 }
 
-fn read_compact_size(transaction_bytes: &mut &[u8]) -> Result<u64, IOError> {
+fn read_compact_size<R: Read>(transaction_bytes: &mut R) -> Result<u64, IOError> {
     let mut compact_size = [0; 1];
-    transaction_bytes.read(&mut compact_size)?;
+    transaction_bytes.read_exact(&mut compact_size)?;
 
     match compact_size[0] {
         0..=252 => Ok(compact_size[0] as u64),
         253 => {
             let mut buffer = [0; 2];
-            transaction_bytes.read(&mut buffer)?;
+            transaction_bytes.read_exact(&mut buffer)?;
             Ok(u16::from_le_bytes(buffer) as u64)
         },
         254 => {
             let mut buffer = [0; 4];
-            transaction_bytes.read(&mut buffer)?;
+            transaction_bytes.read_exact(&mut buffer)?;
             Ok(u32::from_le_bytes(buffer) as u64)
         },
         255 => {
             let mut buffer = [0; 8];
-            transaction_bytes.read(&mut buffer)?;
+            transaction_bytes.read_exact(&mut buffer)?;
             Ok(u64::from_le_bytes(buffer))
         }
     }
 }
 
-fn read_txid(transaction_bytes: &mut &[u8]) -> Result<Txid, IOError> {
+fn read_txid<R: Read>(transaction_bytes: &mut R) -> Result<Txid, IOError> {
     let mut buffer = [0; 32];
-    transaction_bytes.read(&mut buffer)?;
+    transaction_bytes.read_exact(&mut buffer)?;
     Ok(Txid::from_bytes(buffer))
 }
 
-fn read_script(transaction_bytes: &mut &[u8]) -> Result<String, IOError> {
-    let script_size = read_compact_size(transaction_bytes)? as usize;
-    let mut buffer = vec![0_u8; script_size];
-    transaction_bytes.read(&mut buffer)?;
-    Ok(hex::encode(buffer))
-}
-
 fn hash_transaction(raw_transaction: &[u8]) -> Txid {
     // create a sha256 object
     let mut hasher = Sha256::new();
 
     // write the input message
-    hasher.update(&raw_transaction);
+    hasher.update(raw_transaction);
 
     // read digest, consumer hasher
     let hash1 = hasher.finalize();
@@ -78,7 +72,97 @@ fn hash_transaction(raw_transaction: &[u8]) -> Txid {
     Txid::from_bytes(hash2.into())
 }
 
-pub fn get_arg() -> String {
+// A labeled range of bytes in the raw transaction
+#[derive(Debug, PartialEq)]
+pub struct Field {
+    pub label: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+// Reads from the transaction bytes while recording where each field starts and ends
+struct FieldReader<'a> {
+    bytes: &'a [u8],
+    remaining: &'a [u8],
+    fields: Vec<Field>,
+}
+
+impl<'a> FieldReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        FieldReader { bytes, remaining: bytes, fields: vec![] }
+    }
+
+    fn position(&self) -> usize {
+        self.bytes.len() - self.remaining.len()
+    }
+
+    fn read<T, F>(&mut self, label: String, read_field: F) -> Result<T, IOError>
+    where
+        F: FnOnce(&mut &'a [u8]) -> Result<T, IOError>,
+    {
+        let start = self.position();
+        let value = read_field(&mut self.remaining)?;
+        self.fields.push(Field { label, start, end: self.position() });
+        Ok(value)
+    }
+}
+
+fn read_bytes<R: Read>(transaction_bytes: &mut R, len: usize) -> Result<Vec<u8>, IOError> {
+    let mut buffer = vec![0_u8; len];
+    transaction_bytes.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+// Advances past len bytes without copying them
+fn skip_bytes(transaction_bytes: &mut &[u8], len: usize) -> Result<(), IOError> {
+    if len > transaction_bytes.len() {
+        return Err(IOError::from(ErrorKind::UnexpectedEof));
+    }
+    *transaction_bytes = &transaction_bytes[len..];
+    Ok(())
+}
+
+// The shape of a transaction without any of its contents
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Counts {
+    pub version: u32,
+    pub inputs: u64,
+    pub outputs: u64,
+    pub lock_time: u32,
+}
+
+// Reads only the version, counts and locktime, using each script's length to
+// skip over it, so nothing is allocated however large the transaction is
+pub fn count_only(mut transaction_bytes: &[u8]) -> Result<Counts, IOError> {
+    let bytes = &mut transaction_bytes;
+    let version = read_u32(bytes)?;
+
+    let inputs = read_compact_size(bytes)?;
+    for _ in 0..inputs {
+        skip_bytes(bytes, 32 + 4)?; // txid and vout
+        let script_size = read_compact_size(bytes)?;
+        skip_bytes(bytes, (script_size as usize).saturating_add(4))?; // script and sequence
+    }
+
+    let outputs = read_compact_size(bytes)?;
+    for _ in 0..outputs {
+        skip_bytes(bytes, 8)?; // amount
+        let script_size = read_compact_size(bytes)?;
+        skip_bytes(bytes, script_size as usize)?;
+    }
+
+    let lock_time = read_u32(bytes)?;
+    Ok(Counts { version, inputs, outputs, lock_time })
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub hexdump: bool,
+    pub explain: bool,
+    pub count_only: bool,
+}
+
+pub fn get_args() -> (String, Options) {
     let matches = Command::new("Bitcoin Transaction Decoder")
         .version("1.0")
         .about("Decodes a raw transaction")
@@ -87,54 +171,77 @@ pub fn get_arg() -> String {
                 .value_parser(value_parser!(String))
                 .required(true)
         )
+        .arg(
+            arg!(--hexdump "Print the raw bytes with each field labeled")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["explain", "count-only"])
+        )
+        .arg(
+            arg!(--explain "Describe each field of the transaction in plain English")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("count-only")
+        )
+        .arg(
+            arg!(--"count-only" "Only report the version, input and output counts and locktime")
+                .action(ArgAction::SetTrue)
+        )
         .get_matches();
 
-    matches
+    let raw_transaction = matches
         .get_one::<String>("RAW_TRANSACTION")
         .cloned()
-        .expect("raw transaction is required")
+        .expect("raw transaction is required");
+
+    let options = Options {
+        hexdump: matches.get_flag("hexdump"),
+        explain: matches.get_flag("explain"),
+        count_only: matches.get_flag("count-only"),
+    };
+
+    (raw_transaction, options)
 }
 
-pub fn run(raw_transaction_hex: String) -> Result<String, Box<dyn Error>> {
-    let transaction_bytes = hex::decode(raw_transaction_hex).map_err(|e| format!("Hex decoding error: {}", e))?;
-    let mut bytes_slice = transaction_bytes.as_slice();
-    let version = read_u32(&mut bytes_slice)?;
+pub fn decode_transaction(transaction_bytes: &[u8]) -> Result<(Transaction, Vec<Field>), IOError> {
+    let mut reader = FieldReader::new(transaction_bytes);
+    let version = reader.read("version".to_string(), read_u32)?;
 
     // Read inputs
-    let input_length = read_compact_size(&mut bytes_slice)?;
+    let input_length = reader.read("input count".to_string(), read_compact_size)?;
     let mut inputs = vec![];
 
-    for _ in 0..input_length {
-        let txid = read_txid(&mut bytes_slice)?;
-        let output_index = read_u32(&mut bytes_slice)?;
-        let script = read_script(&mut bytes_slice)?;
-        let sequence = read_u32(&mut bytes_slice)?;
+    for i in 0..input_length {
+        let txid = reader.read(format!("input {} txid", i), read_txid)?;
+        let output_index = reader.read(format!("input {} vout", i), read_u32)?;
+        let script_size = reader.read(format!("input {} script length", i), read_compact_size)?;
+        let script = reader.read(format!("input {} script", i), |bytes| read_bytes(bytes, script_size as usize))?;
+        let sequence = reader.read(format!("input {} sequence", i), read_u32)?;
 
         inputs.push(Input {
             txid,
             output_index,
-            script,
+            script: hex::encode(script),
             sequence,
         });
     }
 
     // Read outputs
-    let output_length = read_compact_size(&mut bytes_slice)?;
+    let output_length = reader.read("output count".to_string(), read_compact_size)?;
     let mut outputs = vec![];
 
-    for _ in 0..output_length {
-        let amount = read_amount(&mut bytes_slice)?;
-        let script_pubkey = read_script(&mut bytes_slice)?;
+    for i in 0..output_length {
+        let amount = reader.read(format!("output {} amount", i), read_amount)?;
+        let script_size = reader.read(format!("output {} script length", i), read_compact_size)?;
+        let script_pubkey = reader.read(format!("output {} script", i), |bytes| read_bytes(bytes, script_size as usize))?;
 
         outputs.push(Output {
             amount,
-            script_pubkey,
+            script_pubkey: hex::encode(script_pubkey),
         });
     }
 
-    let lock_time = read_u32(&mut bytes_slice)?;
+    let lock_time = reader.read("locktime".to_string(), read_u32)?;
 
-    let txid = hash_transaction(&transaction_bytes);
+    let txid = hash_transaction(transaction_bytes);
 
     let transaction = Transaction {
         txid,
@@ -144,12 +251,93 @@ pub fn run(raw_transaction_hex: String) -> Result<String, Box<dyn Error>> {
         lock_time,
     };
 
+    Ok((transaction, reader.fields))
+}
+
+// Prints 16 bytes per line with the offset on the left and the field label on the right
+fn hexdump(transaction_bytes: &[u8], fields: &[Field]) -> String {
+    let mut lines = vec![];
+    for field in fields {
+        let bytes = &transaction_bytes[field.start..field.end];
+        for (i, chunk) in bytes.chunks(16).enumerate() {
+            let hex_bytes = chunk.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ");
+            let label = if i == 0 { field.label.as_str() } else { "" };
+            let line = format!("{:08x}  {:<47}  {}", field.start + i * 16, hex_bytes, label);
+            lines.push(line.trim_end().to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+// Describes what a single field's bytes mean, using the label to know how to read them
+fn describe_field(label: &str, mut bytes: &[u8]) -> Result<String, IOError> {
+    let description = if label.ends_with("count") || label.ends_with("length") {
+        format!("the {} ({})", label, read_compact_size(&mut bytes)?)
+    } else if label.ends_with("txid") {
+        format!("the {} ({} when displayed, since txids are shown in reverse byte order)", label, read_txid(&mut bytes)?)
+    } else if label.ends_with("amount") {
+        format!("the {} ({} satoshis, little-endian)", label, read_amount(&mut bytes)?.to_sat())
+    } else if label.ends_with("script") {
+        format!("the {}", label)
+    } else {
+        format!("the {} ({}, little-endian)", label, read_u32(&mut bytes)?)
+    };
+    Ok(description)
+}
+
+// Walks through every field in order and narrates what its bytes are
+fn explain(transaction_bytes: &[u8], fields: &[Field]) -> Result<String, IOError> {
+    let mut sentences = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let bytes = &transaction_bytes[field.start..field.end];
+        let description = describe_field(&field.label, bytes)?;
+        let position = if i == 0 { "The first" } else { "The next" };
+        let sentence = match bytes.len() {
+            0 => format!("{} is empty.", capitalize(&description)),
+            1 => format!("{} byte `{}` is {}.", position, hex::encode(bytes), description),
+            len => format!("{} {} bytes `{}` are {}.", position, len, hex::encode(bytes), description),
+        };
+        sentences.push(sentence);
+    }
+    Ok(sentences.join("\n"))
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub fn run(raw_transaction_hex: String) -> Result<String, Box<dyn Error>> {
+    run_with_options(raw_transaction_hex, &Options::default())
+}
+
+pub fn run_with_options(raw_transaction_hex: String, options: &Options) -> Result<String, Box<dyn Error>> {
+    let transaction_bytes = hex::decode(raw_transaction_hex).map_err(|e| format!("Hex decoding error: {}", e))?;
+    if options.count_only {
+        return Ok(serde_json::to_string_pretty(&count_only(&transaction_bytes)?)?);
+    }
+    let (transaction, fields) = decode_transaction(&transaction_bytes)?;
+
+    if options.hexdump {
+        return Ok(hexdump(&transaction_bytes, &fields));
+    }
+
+    if options.explain {
+        return Ok(explain(&transaction_bytes, &fields)?);
+    }
+
     Ok(serde_json::to_string_pretty(&transaction)?)
 }
 
 #[cfg(test)]
 mod unit_tests {
-    use super::read_compact_size;
+    use super::{count_only, decode_transaction, explain, hexdump, read_compact_size, Counts, Field};
+    use std::io::Cursor;
+
+    const TRANSACTION_HEX: &str = "010000000242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffff75c87cc5f3150eefc1c04c0246e7e0b370e64b17d6226c44b333a6f4ca14b49c000000006b483045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c47012103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abffeffffff02ebb10f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac508c1e000000000017a91476c0c8f2fc403c5edaea365f6a284317b9cdf7258700000000";
 
     #[test]
     fn test_reading_compact_size() {
@@ -184,4 +372,71 @@ mod unit_tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), expected_length);
     }
+
+    #[test]
+    fn test_reading_compact_size_from_cursor() {
+        let mut cursor = Cursor::new(vec![253_u8, 0x20, 0x4e, 254, 0, 0, 0, 1]);
+        assert_eq!(read_compact_size(&mut cursor).unwrap(), 20_000_u64);
+        assert_eq!(cursor.position(), 3);
+        assert_eq!(read_compact_size(&mut cursor).unwrap(), 256_u64.pow(3));
+        assert_eq!(cursor.position(), 8);
+
+        // running out of bytes is an error rather than a short read
+        assert!(read_compact_size(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let transaction_bytes = hex::decode(TRANSACTION_HEX).unwrap();
+        let (_, fields) = decode_transaction(&transaction_bytes).unwrap();
+        assert_eq!(fields[0], Field { label: "version".to_string(), start: 0, end: 4 });
+        assert_eq!(fields[1], Field { label: "input count".to_string(), start: 4, end: 5 });
+        assert_eq!(fields.last().unwrap().end, transaction_bytes.len());
+
+        let dump = hexdump(&transaction_bytes, &fields);
+        let mut lines = dump.lines();
+        assert_eq!(lines.next().unwrap(), format!("00000000  01 00 00 00{}  version", " ".repeat(36)));
+        assert_eq!(lines.next().unwrap(), format!("00000004  02{}  input count", " ".repeat(45)));
+    }
+
+    #[test]
+    fn test_count_only() {
+        let transaction_bytes = hex::decode(TRANSACTION_HEX).unwrap();
+        let counts = count_only(&transaction_bytes).unwrap();
+        let (transaction, _) = decode_transaction(&transaction_bytes).unwrap();
+        assert_eq!(
+            counts,
+            Counts {
+                version: transaction.version,
+                inputs: transaction.inputs.len() as u64,
+                outputs: transaction.outputs.len() as u64,
+                lock_time: transaction.lock_time,
+            }
+        );
+        assert_eq!(counts, Counts { version: 1, inputs: 2, outputs: 2, lock_time: 0 });
+
+        // a script length running past the end is an error rather than a panic
+        assert!(count_only(&transaction_bytes[..100]).is_err());
+    }
+
+    #[test]
+    fn test_explain() {
+        let transaction_bytes = hex::decode(TRANSACTION_HEX).unwrap();
+        let (_, fields) = decode_transaction(&transaction_bytes).unwrap();
+        let explanation = explain(&transaction_bytes, &fields).unwrap();
+        let sentences: Vec<&str> = explanation.lines().collect();
+        assert_eq!(sentences.len(), fields.len());
+
+        assert_eq!(sentences[0], "The first 4 bytes `01000000` are the version (1, little-endian).");
+        assert_eq!(sentences[1], "The next byte `02` is the input count (2).");
+        assert_eq!(
+            sentences[2],
+            "The next 32 bytes `42d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380` are the input 0 txid \
+            (8073cdf947ac97c23b77b055217da78d3ad71d30e1f6c095be8b30f7d6c1d542 when displayed, since txids are shown in reverse byte order)."
+        );
+        assert_eq!(sentences[3], "The next 4 bytes `01000000` are the input 0 vout (1, little-endian).");
+        assert_eq!(sentences[4], "The next byte `6a` is the input 0 script length (106).");
+        assert!(sentences.contains(&"The next 8 bytes `ebb10f0000000000` are the output 0 amount (1028587 satoshis, little-endian)."));
+        assert_eq!(sentences.last().unwrap(), &"The next 4 bytes `00000000` are the locktime (0, little-endian).");
+    }
 }
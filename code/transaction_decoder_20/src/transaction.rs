@@ -1,4 +1,5 @@
 use serde::{Serialize, Serializer};
+use std::fmt;
 
 #[derive(Debug, Serialize)]
 pub struct Transaction {
@@ -18,11 +19,18 @@ impl Txid {
     }
 }
 
+// Txids are displayed in the reverse of their byte order
+impl fmt::Display for Txid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut bytes = self.0;
+        bytes.reverse();
+        write!(f, "{}", hex::encode(bytes))
+    }
+}
+
 impl Serialize for Txid {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let mut bytes = self.0.clone();
-        bytes.reverse();
-        s.serialize_str(&hex::encode(bytes))
+        s.serialize_str(&self.to_string())
     }
 }
 
@@ -41,6 +49,10 @@ impl Amount {
     pub fn from_sat(satoshi: u64) -> Amount {
         Amount(satoshi)
     }
+
+    pub fn to_sat(&self) -> u64 {
+        self.0
+    }
 }
 
 trait BitcoinValue {
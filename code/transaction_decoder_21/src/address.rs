@@ -0,0 +1,63 @@
+use sha2::{Digest, Sha256};
+
+const OP_0: u8 = 0x00;
+const OP_1: u8 = 0x51;
+const OP_RETURN: u8 = 0x6a;
+const OP_DUP: u8 = 0x76;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKSIG: u8 = 0xac;
+
+// Names the standard output script templates, following Bitcoin Core's naming
+pub fn script_type(script: &[u8]) -> &'static str {
+    match script {
+        [OP_DUP, OP_HASH160, 0x14, .., OP_EQUALVERIFY, OP_CHECKSIG] if script.len() == 25 => "pubkeyhash",
+        [OP_HASH160, 0x14, .., OP_EQUAL] if script.len() == 23 => "scripthash",
+        [OP_0, 0x14, ..] if script.len() == 22 => "witness_v0_keyhash",
+        [OP_0, 0x20, ..] if script.len() == 34 => "witness_v0_scripthash",
+        [OP_1, 0x20, ..] if script.len() == 34 => "witness_v1_taproot",
+        [OP_RETURN, ..] => "nulldata",
+        _ => "nonstandard",
+    }
+}
+
+// The mainnet base58 address a P2PKH or P2SH output script pays to. Segwit
+// outputs use bech32 addresses, which come with segwit decoding in the next chapter.
+pub fn address(script: &[u8]) -> Option<String> {
+    match script_type(script) {
+        "pubkeyhash" => Some(base58check(0x00, &script[3..23])),
+        "scripthash" => Some(base58check(0x05, &script[2..22])),
+        _ => None,
+    }
+}
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn base58check(version: u8, payload: &[u8]) -> String {
+    let mut data = vec![version];
+    data.extend_from_slice(payload);
+    let checksum = Sha256::digest(Sha256::digest(&data));
+    data.extend_from_slice(&checksum[..4]);
+
+    // repeatedly divide the big-endian number by 58, collecting the remainders
+    let mut digits: Vec<u8> = vec![];
+    for &byte in data.iter() {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    // each leading zero byte is written as a '1'
+    let leading_zeros = data.iter().take_while(|&&byte| byte == 0).count();
+    let mut address = "1".repeat(leading_zeros);
+    address.extend(digits.iter().rev().map(|&digit| BASE58_ALPHABET[digit as usize] as char));
+    address
+}
@@ -0,0 +1,29 @@
+use std::io::{Read, Result};
+
+// Wraps a reader and keeps track of how many bytes have been read through it
+pub struct CountingReader<R: Read> {
+    inner: R,
+    position: usize,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        CountingReader { inner, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.position += len;
+        Ok(len)
+    }
+}
@@ -1,32 +1,342 @@
+pub mod address;
+pub mod io;
 mod transaction;
-use self::transaction::{Decodable, Transaction,};
+use self::io::CountingReader;
+use self::transaction::{Decodable, Encodable, Transaction};
+pub use self::transaction::Limits;
 use std::error::Error;
-use clap::{arg, value_parser, Command};
+use std::io::{ErrorKind, Read};
+use clap::{arg, value_parser, ArgAction, Command};
+use serde::Serialize;
+use serde_json::{json, Value};
 
-pub fn get_arg() -> String {
+#[derive(Debug, Default)]
+pub struct Options {
+    pub limits: Limits,
+    pub field: Option<String>,
+    pub lenient: bool,
+    pub csv: bool,
+    // the raw transaction is a JSON object holding the hex in this field
+    pub from_json: Option<String>,
+    pub self_check: bool,
+    pub length_prefixed: bool,
+    // removed from the start of the raw transaction, e.g. when pasted from a URI
+    pub strip_prefix: Option<String>,
+    pub oneline: bool,
+    // the raw transaction is a JSON array of hex strings to decode in turn
+    pub json_array: bool,
+}
+
+pub fn get_args() -> (String, Options) {
     let matches = Command::new("Bitcoin Transaction Decoder")
         .version("1.0")
         .about("Decodes a raw transaction")
         .arg(
             arg!([RAW_TRANSACTION])
                 .value_parser(value_parser!(String))
-                .required(true)
+                .required_unless_present_any(["from-json", "json-array"])
+        )
+        .arg(
+            arg!(--"max-inputs" <N> "Reject transactions with more than N inputs")
+                .value_parser(value_parser!(u64))
+        )
+        .arg(
+            arg!(--"max-outputs" <N> "Reject transactions with more than N outputs")
+                .value_parser(value_parser!(u64))
+        )
+        .arg(
+            arg!(--field <PATH> "Print a single value, e.g. txid or outputs.0.amount")
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            arg!(--lenient "On a parse error, print whatever was decoded before it")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--csv "Print one CSV row per output instead of JSON")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--oneline "Print a one line summary: txid, version, input and output counts, vsize and total output")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"self-check" "Check that the transaction re-encodes to exactly the same bytes")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"length-prefixed" "The transaction starts with its length as a 4 byte big-endian integer")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"strip-prefix" <PREFIX> "Remove PREFIX from the start of the raw transaction, e.g. tx:")
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            arg!(--"from-json" <FIELD> "Read a JSON object from stdin and decode the hex in FIELD, e.g. hex")
+                .value_parser(value_parser!(String))
+        )
+        .arg(
+            arg!(--"json-array" "Read a JSON array of hex strings from stdin and decode each of them")
+                .action(ArgAction::SetTrue)
         )
         .get_matches();
 
-    matches
-        .get_one::<String>("RAW_TRANSACTION")
-        .cloned()
-        .expect("raw transaction is required")
+    let from_json = matches.get_one::<String>("from-json").cloned();
+    let json_array = matches.get_flag("json-array");
+    let raw_transaction = if from_json.is_some() || json_array {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input).expect("should be able to read stdin");
+        input
+    } else {
+        matches
+            .get_one::<String>("RAW_TRANSACTION")
+            .cloned()
+            .expect("raw transaction is required")
+    };
+
+    let options = Options {
+        limits: Limits {
+            max_inputs: matches.get_one::<u64>("max-inputs").copied(),
+            max_outputs: matches.get_one::<u64>("max-outputs").copied(),
+        },
+        field: matches.get_one::<String>("field").cloned(),
+        lenient: matches.get_flag("lenient"),
+        csv: matches.get_flag("csv"),
+        from_json,
+        self_check: matches.get_flag("self-check"),
+        length_prefixed: matches.get_flag("length-prefixed"),
+        strip_prefix: matches.get_one::<String>("strip-prefix").cloned(),
+        oneline: matches.get_flag("oneline"),
+        json_array,
+    };
+
+    (raw_transaction, options)
 }
 
 pub fn decode(raw_transaction_hex: String) -> Result<Transaction, Box<dyn Error>> {
-    let transaction_bytes = hex::decode(raw_transaction_hex).map_err(|e| format!("Hex decoding error: {}", e))?;
+    decode_with_options(raw_transaction_hex, &Options::default())
+}
+
+pub fn decode_with_options(raw_transaction_hex: String, options: &Options) -> Result<Transaction, Box<dyn Error>> {
+    let transaction_bytes = transaction_bytes(&raw_transaction_hex, options)?;
     let mut bytes_slice = transaction_bytes.as_slice();
-    Ok(Transaction::consensus_decode(&mut bytes_slice)?)
+    Ok(Transaction::consensus_decode_with_limits(&mut bytes_slice, &options.limits)?)
+}
+
+// The raw transaction's bytes, without the length prefix if there is one
+fn transaction_bytes(raw_transaction_hex: &str, options: &Options) -> Result<Vec<u8>, Box<dyn Error>> {
+    let raw_transaction_hex = sanitize_hex(raw_transaction_hex, options.strip_prefix.as_deref());
+    let bytes = hex::decode(raw_transaction_hex).map_err(|e| format!("Hex decoding error: {}", e))?;
+    if options.length_prefixed {
+        return Ok(strip_length_prefix(&bytes)?.to_vec());
+    }
+    Ok(bytes)
+}
+
+// Cleans up hex pasted from QR codes, URIs and the like: removes the prefix if
+// it's there and any whitespace, dashes, colons or underscores used to group the
+// digits. Anything else that isn't hex is left for the hex decoder to report.
+pub fn sanitize_hex(input: &str, prefix: Option<&str>) -> String {
+    let input = input.trim();
+    let input = match prefix {
+        Some(prefix) => input.strip_prefix(prefix).unwrap_or(input),
+        None => input,
+    };
+    input.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | ':' | '_'))
+        .collect()
+}
+
+// Some tools frame a raw transaction with its length as a 4 byte big-endian
+// integer. Returns the transaction after checking it's exactly that long.
+pub fn strip_length_prefix(bytes: &[u8]) -> Result<&[u8], transaction::Error> {
+    if bytes.len() < 4 {
+        return Err(transaction::Error::ParseFailed("missing length prefix"));
+    }
+    let (prefix, transaction) = bytes.split_at(4);
+    let len = u32::from_be_bytes(prefix.try_into().expect("split at 4 bytes"));
+    if len as usize != transaction.len() {
+        return Err(transaction::Error::ParseFailed("length prefix doesn't match the transaction length"));
+    }
+    Ok(transaction)
+}
+
+// Decodes back to back raw transactions, such as a file made by concatenating
+// many binary transactions, until the reader runs out of bytes
+pub fn decode_all_from_reader<R: Read>(r: &mut R) -> Result<Vec<Transaction>, transaction::Error> {
+    let mut reader = CountingReader::new(r);
+    let mut transactions = vec![];
+    loop {
+        let start = reader.position();
+        match Transaction::consensus_decode(&mut reader) {
+            Ok(transaction) => transactions.push(transaction),
+            // running out of bytes exactly where a transaction would start means we're done
+            Err(transaction::Error::Io(ref e))
+                if e.kind() == ErrorKind::UnexpectedEof && reader.position() == start =>
+            {
+                return Ok(transactions);
+            }
+            Err(e) => return Err(e),
+        }
+    }
 }
 
 pub fn run(raw_transaction_hex: String) -> Result<String, Box<dyn Error>> {
-    let transaction = decode(raw_transaction_hex)?;
-    Ok(serde_json::to_string_pretty(&transaction)?)
+    run_with_options(raw_transaction_hex, &Options::default())
+}
+
+// Pulls the raw hex out of a JSON object, such as the result of Bitcoin Core's
+// `getrawtransaction <txid> true`
+pub fn hex_from_json(json: &str, field: &str) -> Result<String, Box<dyn Error>> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("JSON parsing error: {}", e))?;
+    match value.get(field) {
+        Some(Value::String(hex)) => Ok(hex.clone()),
+        Some(_) => Err(format!("field {} is not a string", field).into()),
+        None => Err(format!("missing field: {}", field).into()),
+    }
+}
+
+pub fn run_with_options(raw_transaction_hex: String, options: &Options) -> Result<String, Box<dyn Error>> {
+    if options.json_array {
+        return decode_json_array(&raw_transaction_hex, options);
+    }
+    let raw_transaction_hex = match &options.from_json {
+        Some(field) => hex_from_json(&raw_transaction_hex, field)?,
+        None => raw_transaction_hex,
+    };
+    if options.self_check {
+        return self_check(&transaction_bytes(&raw_transaction_hex, options)?);
+    }
+    if options.lenient {
+        let transaction_bytes = transaction_bytes(&raw_transaction_hex, options)?;
+        let mut bytes_slice = transaction_bytes.as_slice();
+        return match Transaction::consensus_decode_lenient(&mut bytes_slice, &options.limits) {
+            Ok(transaction) if options.csv => Ok(to_csv(&transaction)),
+            Ok(transaction) if options.oneline => to_oneline(&transaction),
+            Ok(transaction) => to_output(&transaction, options),
+            Err(partial) => to_output(&partial, options),
+        };
+    }
+    let transaction = decode_with_options(raw_transaction_hex, options)?;
+    if options.csv {
+        return Ok(to_csv(&transaction));
+    }
+    if options.oneline {
+        return to_oneline(&transaction);
+    }
+    to_output(&transaction, options)
+}
+
+// Decodes each hex string in a JSON array, keeping their order. A string that
+// fails to decode becomes an object with its index and the error instead.
+fn decode_json_array(json: &str, options: &Options) -> Result<String, Box<dyn Error>> {
+    let items: Vec<Value> = serde_json::from_str(json).map_err(|e| format!("JSON parsing error: {}", e))?;
+    let decoded: Vec<Value> = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let result = match item {
+                Value::String(hex) => decode_with_options(hex, options)
+                    .and_then(|transaction| Ok(serde_json::to_value(&transaction)?)),
+                _ => Err("not a hex string".into()),
+            };
+            result.unwrap_or_else(|e| json!({ "index": index, "error": e.to_string() }))
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&decoded)?)
+}
+
+// Decodes and re-encodes the transaction, reporting the first byte where the two differ
+fn self_check(original: &[u8]) -> Result<String, Box<dyn Error>> {
+    let transaction = Transaction::consensus_decode(&mut &original[..])?;
+    let mut encoded = vec![];
+    transaction.consensus_encode(&mut encoded)?;
+
+    let mismatch = original.iter().zip(encoded.iter()).position(|(a, b)| a != b);
+    match mismatch {
+        None if original.len() == encoded.len() => Ok(format!("ok: all {} bytes round-trip", original.len())),
+        None => Ok(format!(
+            "mismatch: re-encoded to {} bytes but the input has {}",
+            encoded.len(), original.len(),
+        )),
+        Some(offset) => Ok(format!(
+            "mismatch at byte {}: expected {:02x}, re-encoded as {:02x}",
+            offset, original[offset], encoded[offset],
+        )),
+    }
+}
+
+fn to_csv(transaction: &Transaction) -> String {
+    let txid = transaction.txid().to_string();
+    let mut rows = vec!["txid,n,value_sat,script_type,address".to_string()];
+    for (n, output) in transaction.outputs.iter().enumerate() {
+        let script_pubkey = hex::decode(&output.script_pubkey).unwrap_or_default();
+        let row = [
+            txid.clone(),
+            n.to_string(),
+            output.amount.to_sat().to_string(),
+            address::script_type(&script_pubkey).to_string(),
+            address::address(&script_pubkey).unwrap_or_default(),
+        ];
+        rows.push(row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+    }
+    rows.join("\n")
+}
+
+// A summary on a single line, for grepping through many decodes, e.g.
+// `<txid> v2 1in/2out 141vB 0.03050000BTC segwit rbf`
+fn to_oneline(transaction: &Transaction) -> Result<String, Box<dyn Error>> {
+    let total_output = transaction.total_output().ok_or("output amounts add up to more than u64::MAX satoshis")?;
+    let mut line = format!(
+        "{} v{} {}in/{}out {}vB {}BTC",
+        transaction.txid(),
+        transaction.version.0,
+        transaction.inputs.len(),
+        transaction.outputs.len(),
+        transaction.vsize(),
+        total_output.to_btc_string(),
+    );
+    if transaction.is_segwit() {
+        line.push_str(" segwit");
+    }
+    if transaction.signals_rbf() {
+        line.push_str(" rbf");
+    }
+    Ok(line)
+}
+
+// Quotes a field if it contains a comma, quote or newline, doubling any quotes inside it
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn to_output<T: Serialize>(value: &T, options: &Options) -> Result<String, Box<dyn Error>> {
+    match &options.field {
+        Some(path) => get_field(&serde_json::to_value(value)?, path),
+        None => Ok(serde_json::to_string_pretty(value)?),
+    }
+}
+
+fn get_field(json: &Value, path: &str) -> Result<String, Box<dyn Error>> {
+    // the transaction id key has a space in it, so accept txid as well
+    let path = if path == "txid" { "transaction id" } else { path };
+
+    let value = path
+        .split('.')
+        .try_fold(json, |value, key| match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => None,
+        })
+        .ok_or_else(|| format!("unknown field: {}", path))?;
+
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        value => Ok(value.to_string()),
+    }
 }
@@ -1,6 +1,6 @@
-
 fn main() {
-    match transaction_decoder_21::run(transaction_decoder_21::get_arg()) {
+    let (raw_transaction, options) = transaction_decoder_21::get_args();
+    match transaction_decoder_21::run_with_options(raw_transaction, &options) {
         Ok(json) => println!("{}", json),
         Err(e) => eprintln!("{}", e),
     }
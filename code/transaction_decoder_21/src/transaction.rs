@@ -7,12 +7,14 @@ use sha2::{Digest, Sha256};
 #[derive(Debug)]
 pub enum Error {
     Io(std::io::Error),
+    ParseFailed(&'static str),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Error::Io(ref e) => write!(f, "IO error: {}", e)
+            Error::Io(ref e) => write!(f, "IO error: {}", e),
+            Error::ParseFailed(s) => write!(f, "parse failed: {}", s),
         }
     }
 }
@@ -29,12 +31,17 @@ pub struct Transaction {
 
 impl Transaction {
     pub fn txid(&self) -> Txid {
-        let mut txid_data = Vec::new();
-        self.version.consensus_encode(&mut txid_data).unwrap();
-        self.inputs.consensus_encode(&mut txid_data).unwrap();
-        self.outputs.consensus_encode(&mut txid_data).unwrap();
-        self.lock_time.consensus_encode(&mut txid_data).unwrap();
-        Txid::new(txid_data)
+        Txid::new(self.legacy_bytes())
+    }
+
+    // The serialization without the segwit marker, flag and witnesses
+    fn legacy_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.version.consensus_encode(&mut bytes).unwrap();
+        self.inputs.consensus_encode(&mut bytes).unwrap();
+        self.outputs.consensus_encode(&mut bytes).unwrap();
+        self.lock_time.consensus_encode(&mut bytes).unwrap();
+        bytes
     }
 }
 
@@ -69,11 +76,17 @@ impl Txid {
     }
 }
 
+impl fmt::Display for Txid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut bytes = self.0;
+        bytes.reverse();
+        write!(f, "{}", hex::encode(bytes))
+    }
+}
+
 impl Serialize for Txid {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let mut bytes = self.0.clone();
-        bytes.reverse();
-        s.serialize_str(&hex::encode(bytes))
+        s.serialize_str(&self.to_string())
     }
 }
 
@@ -85,6 +98,9 @@ pub struct TxIn {
     pub previous_txid: Txid,
     pub previous_vout: u32,
     pub script_sig: String,
+    // hex encoded witness items, empty for inputs without a witness
+    #[serde(rename = "txinwitness", skip_serializing_if = "Vec::is_empty")]
+    pub witness: Vec<String>,
     pub sequence: u32,
 }
 
@@ -194,7 +210,7 @@ impl Encodable for Vec<TxIn> {
 
 impl Encodable for Txid {
     fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
-        Ok(self.0.consensus_encode(w)?)
+        self.0.consensus_encode(w)
     }
 }
 
@@ -236,6 +252,58 @@ impl Encodable for TxOut {
     }
 }
 
+impl Transaction {
+    pub fn is_segwit(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    // BIP141 weight: 4 units per byte without the witness data, 1 per witness byte
+    pub fn weight(&self) -> u64 {
+        let base_size = self.legacy_bytes().len() as u64;
+        let total_size = self.consensus_encode(&mut std::io::sink()).unwrap() as u64;
+        base_size * 3 + total_size
+    }
+
+    pub fn vsize(&self) -> u64 {
+        self.weight().div_ceil(4)
+    }
+
+    // BIP125: any input with a sequence below 0xfffffffe makes the transaction replaceable
+    pub fn signals_rbf(&self) -> bool {
+        self.inputs.iter().any(|input| input.sequence < 0xfffffffe)
+    }
+
+    // Returns None if the amounts add up to more than a u64 holds
+    pub fn total_output(&self) -> Option<Amount> {
+        self.outputs.iter()
+            .try_fold(0u64, |total, output| total.checked_add(output.amount.0))
+            .map(Amount)
+    }
+}
+
+// The full serialization, including the segwit marker, flag and witnesses if there are any
+impl Encodable for Transaction {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
+        let mut len = self.version.consensus_encode(w)?;
+        if self.is_segwit() {
+            len += 0_u8.consensus_encode(w)?;
+            len += 1_u8.consensus_encode(w)?;
+        }
+        len += self.inputs.consensus_encode(w)?;
+        len += self.outputs.consensus_encode(w)?;
+        if self.is_segwit() {
+            for input in self.inputs.iter() {
+                len += CompactSize(input.witness.len() as u64).consensus_encode(w)?;
+                for item in input.witness.iter() {
+                    len += item.consensus_encode(w)?;
+                }
+            }
+        }
+        len += self.lock_time.consensus_encode(w)?;
+        Ok(len)
+    }
+}
+
 pub trait Decodable: Sized {
     fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, Error>;
 }
@@ -334,6 +402,7 @@ impl Decodable for TxIn {
             previous_txid: Txid::consensus_decode(r)?,
             previous_vout: u32::consensus_decode(r)?,
             script_sig: String::consensus_decode(r)?,
+            witness: vec![],
             sequence: u32::consensus_decode(r)?,
         })
     }
@@ -361,11 +430,94 @@ impl Decodable for TxOut {
 
 impl Decodable for Transaction {
     fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, Error> {
+        Transaction::consensus_decode_with_limits(r, &Limits::default())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Limits {
+    pub max_inputs: Option<u64>,
+    pub max_outputs: Option<u64>,
+}
+
+fn check_limit(count: u64, limit: Option<u64>, error: &'static str) -> Result<(), Error> {
+    match limit {
+        Some(max) if count > max => Err(Error::ParseFailed(error)),
+        _ => Ok(()),
+    }
+}
+
+// Whatever was decoded before hitting an error, for inspecting malformed transactions
+#[derive(Debug, Default, Serialize)]
+pub struct PartialTransaction {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    pub inputs: Vec<TxIn>,
+    pub outputs: Vec<TxOut>,
+    #[serde(rename = "locktime", skip_serializing_if = "Option::is_none")]
+    pub lock_time: Option<u32>,
+    pub error: String,
+}
+
+impl Transaction {
+    // Checks the input and output counts against the limits as soon as they're read,
+    // so we never try to parse more entries than the caller is willing to accept
+    pub fn consensus_decode_with_limits<R: Read>(r: &mut R, limits: &Limits) -> Result<Self, Error> {
+        Transaction::decode_into(r, limits, &mut PartialTransaction::default())
+    }
+
+    // Same as consensus_decode_with_limits, but on failure returns the fields
+    // that were successfully decoded along with the error
+    pub fn consensus_decode_lenient<R: Read>(r: &mut R, limits: &Limits) -> Result<Self, PartialTransaction> {
+        let mut partial = PartialTransaction::default();
+        Transaction::decode_into(r, limits, &mut partial).map_err(|e| {
+            partial.error = e.to_string();
+            partial
+        })
+    }
+
+    fn decode_into<R: Read>(r: &mut R, limits: &Limits, partial: &mut PartialTransaction) -> Result<Self, Error> {
+        let version = Version::consensus_decode(r)?;
+        partial.version = Some(version.0);
+
+        // a zero input count is the segwit marker, which is followed by the flag
+        let mut input_count = CompactSize::consensus_decode(r)?.0;
+        let segwit = input_count == 0;
+        if segwit {
+            if u8::consensus_decode(r)? != 1 {
+                return Err(Error::ParseFailed("unsupported segwit flag"));
+            }
+            input_count = CompactSize::consensus_decode(r)?.0;
+        }
+        check_limit(input_count, limits.max_inputs, "too many inputs")?;
+        for _ in 0..input_count {
+            partial.inputs.push(TxIn::consensus_decode(r)?);
+        }
+
+        let output_count = CompactSize::consensus_decode(r)?.0;
+        check_limit(output_count, limits.max_outputs, "too many outputs")?;
+        for _ in 0..output_count {
+            partial.outputs.push(TxOut::consensus_decode(r)?);
+        }
+
+        if segwit {
+            for input in partial.inputs.iter_mut() {
+                let item_count = CompactSize::consensus_decode(r)?.0;
+                for _ in 0..item_count {
+                    input.witness.push(String::consensus_decode(r)?);
+                }
+            }
+        }
+
+        // read before taking the inputs and outputs, so a truncated locktime
+        // still leaves them in the partial transaction
+        let lock_time = u32::consensus_decode(r)?;
+        partial.lock_time = Some(lock_time);
         Ok(Transaction {
-            version: Version::consensus_decode(r)?,
-            inputs: Vec::<TxIn>::consensus_decode(r)?,
-            outputs: Vec::<TxOut>::consensus_decode(r)?,
-            lock_time: u32::consensus_decode(r)?,
+            version,
+            inputs: std::mem::take(&mut partial.inputs),
+            outputs: std::mem::take(&mut partial.outputs),
+            lock_time,
         })
     }
 }
@@ -377,6 +529,15 @@ impl Amount {
     pub fn from_sat(satoshi: u64) -> Amount {
         Amount(satoshi)
     }
+
+    pub fn to_sat(&self) -> u64 {
+        self.0
+    }
+
+    // Exact to the satoshi, unlike formatting the f64 from to_btc
+    pub fn to_btc_string(&self) -> String {
+        format!("{}.{:08}", self.0 / 100_000_000, self.0 % 100_000_000)
+    }
 }
 
 trait BitcoinValue {
@@ -1,9 +1,287 @@
+use transaction_decoder_21::{address, Limits, Options};
 use std::fs;
 
+const LEGACY_TRANSACTION_HEX: &str = "010000000242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffff75c87cc5f3150eefc1c04c0246e7e0b370e64b17d6226c44b333a6f4ca14b49c000000006b483045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c47012103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abffeffffff02ebb10f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac508c1e000000000017a91476c0c8f2fc403c5edaea365f6a284317b9cdf7258700000000";
+
 #[test]
 fn test_json() {
-    let raw_transaction_hex = "010000000242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffff75c87cc5f3150eefc1c04c0246e7e0b370e64b17d6226c44b333a6f4ca14b49c000000006b483045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c47012103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abffeffffff02ebb10f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac508c1e000000000017a91476c0c8f2fc403c5edaea365f6a284317b9cdf7258700000000";
-    let json = transaction_decoder_21::run(raw_transaction_hex.to_string()).unwrap();
+    let json = transaction_decoder_21::run(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
     let expected = fs::read_to_string("tests/test_transaction.json").unwrap();
     assert_eq!(expected, json);
 }
+
+// three inputs spending made up outpoints and a single output with an empty script
+const THREE_INPUT_TRANSACTION_HEX: &str = "010000000301010101010101010101010101010101010101010101010101010101010101010000000000ffffffff02020202020202020202020202020202020202020202020202020202020202020000000000ffffffff03030303030303030303030303030303030303030303030303030303030303030000000000ffffffff01e8030000000000000000000000";
+
+#[test]
+fn test_max_inputs() {
+    let options = Options {
+        limits: Limits { max_inputs: Some(2), max_outputs: None },
+        ..Default::default()
+    };
+    let result = transaction_decoder_21::run_with_options(THREE_INPUT_TRANSACTION_HEX.to_string(), &options);
+    assert_eq!(result.unwrap_err().to_string(), "parse failed: too many inputs");
+
+    let options = Options {
+        limits: Limits { max_inputs: Some(3), max_outputs: None },
+        ..Default::default()
+    };
+    let result = transaction_decoder_21::run_with_options(THREE_INPUT_TRANSACTION_HEX.to_string(), &options);
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_max_outputs() {
+    let options = Options {
+        limits: Limits { max_inputs: None, max_outputs: Some(0) },
+        ..Default::default()
+    };
+    let result = transaction_decoder_21::run_with_options(THREE_INPUT_TRANSACTION_HEX.to_string(), &options);
+    assert_eq!(result.unwrap_err().to_string(), "parse failed: too many outputs");
+}
+
+fn field_options(path: &str) -> Options {
+    Options {
+        field: Some(path.to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_field_txid() {
+    let result = transaction_decoder_21::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &field_options("txid"));
+    assert_eq!(result.unwrap(), "3c1804567a336c3944e30b3c2593970bfcbf5b15a40f4fc6b626a360ee0507f2");
+}
+
+#[test]
+fn test_field_version() {
+    let result = transaction_decoder_21::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &field_options("version"));
+    assert_eq!(result.unwrap(), "1");
+
+    let result = transaction_decoder_21::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &field_options("outputs.1.amount"));
+    assert_eq!(result.unwrap(), "0.02002");
+}
+
+#[test]
+fn test_field_out_of_range() {
+    let result = transaction_decoder_21::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &field_options("outputs.2.amount"));
+    assert_eq!(result.unwrap_err().to_string(), "unknown field: outputs.2.amount");
+}
+
+#[test]
+fn test_lenient_truncated_after_inputs() {
+    // the first 300 bytes hold the version and both inputs
+    let truncated_hex = &LEGACY_TRANSACTION_HEX[..600];
+    let options = Options {
+        lenient: true,
+        ..Default::default()
+    };
+    let json = transaction_decoder_21::run_with_options(truncated_hex.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["version"], 1);
+    assert_eq!(value["inputs"].as_array().unwrap().len(), 2);
+    assert_eq!(value["inputs"][1]["previous_vout"], 0);
+    assert!(value["outputs"].as_array().unwrap().is_empty());
+    assert!(value.get("locktime").is_none());
+    assert_eq!(value["error"], "IO error: failed to fill whole buffer");
+
+    // strict decoding is still the default
+    let result = transaction_decoder_21::run(truncated_hex.to_string());
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_lenient_truncated_in_locktime() {
+    // only the last 3 bytes of the locktime are missing
+    let truncated_hex = &LEGACY_TRANSACTION_HEX[..LEGACY_TRANSACTION_HEX.len() - 6];
+    let options = Options {
+        lenient: true,
+        ..Default::default()
+    };
+    let json = transaction_decoder_21::run_with_options(truncated_hex.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["inputs"].as_array().unwrap().len(), 2);
+    assert_eq!(value["outputs"].as_array().unwrap().len(), 2);
+    assert!(value.get("locktime").is_none());
+    assert_eq!(value["error"], "IO error: failed to fill whole buffer");
+}
+
+#[test]
+fn test_decode_all_from_reader() {
+    let dump = [LEGACY_TRANSACTION_HEX, LEGACY_TRANSACTION_HEX, THREE_INPUT_TRANSACTION_HEX].concat();
+    let bytes = hex::decode(dump).unwrap();
+    let transactions = transaction_decoder_21::decode_all_from_reader(&mut bytes.as_slice()).unwrap();
+    assert_eq!(transactions.len(), 3);
+
+    let first_txid = serde_json::to_string(&transactions[0].txid()).unwrap();
+    assert_eq!(first_txid, "\"3c1804567a336c3944e30b3c2593970bfcbf5b15a40f4fc6b626a360ee0507f2\"");
+    let last_txid = serde_json::to_string(&transactions[2].txid()).unwrap();
+    assert_eq!(last_txid, "\"c3e572986d6419712767621cffa583578ca14a2038c1636be2a4089552ec6ce9\"");
+}
+
+#[test]
+fn test_decode_all_from_reader_trailing_bytes() {
+    let dump = [LEGACY_TRANSACTION_HEX, "0100"].concat();
+    let bytes = hex::decode(dump).unwrap();
+    assert!(transaction_decoder_21::decode_all_from_reader(&mut bytes.as_slice()).is_err());
+}
+
+#[test]
+fn test_csv() {
+    let options = Options {
+        csv: true,
+        ..Default::default()
+    };
+    let csv = transaction_decoder_21::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &options).unwrap();
+    let mut rows = csv.lines();
+    assert_eq!(rows.next().unwrap(), "txid,n,value_sat,script_type,address");
+    assert_eq!(
+        rows.next().unwrap(),
+        "3c1804567a336c3944e30b3c2593970bfcbf5b15a40f4fc6b626a360ee0507f2,0,1028587,pubkeyhash,18CZSmjX5tjq6hg5V3tX7m8sSERuqXHrWp"
+    );
+    assert_eq!(rows.next().unwrap().split(',').nth(4).unwrap(), "3CWvc7XqLk5wZvJ9tPDkqQGNcSX8HCriLY");
+    assert_eq!(rows.next(), None);
+}
+
+#[test]
+fn test_script_types() {
+    let p2wpkh = hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+    assert_eq!(address::script_type(&p2wpkh), "witness_v0_keyhash");
+    assert_eq!(address::address(&p2wpkh), None);
+
+    let p2tr = hex::decode("512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+    assert_eq!(address::script_type(&p2tr), "witness_v1_taproot");
+
+    let op_return = hex::decode("6a0568656c6c6f").unwrap();
+    assert_eq!(address::script_type(&op_return), "nulldata");
+    assert_eq!(address::address(&op_return), None);
+}
+
+#[test]
+fn test_from_json() {
+    // trimmed down getrawtransaction output
+    let json = format!(
+        r#"{{"txid": "3c1804567a336c3944e30b3c2593970bfcbf5b15a40f4fc6b626a360ee0507f2", "hex": "{}", "confirmations": 10}}"#,
+        LEGACY_TRANSACTION_HEX
+    );
+    let options = Options {
+        from_json: Some("hex".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        transaction_decoder_21::run_with_options(json.clone(), &options).unwrap(),
+        transaction_decoder_21::run(LEGACY_TRANSACTION_HEX.to_string()).unwrap()
+    );
+
+    let options = Options {
+        from_json: Some("rawtx".to_string()),
+        ..Default::default()
+    };
+    let err = transaction_decoder_21::run_with_options(json, &options).unwrap_err();
+    assert_eq!(err.to_string(), "missing field: rawtx");
+}
+
+#[test]
+fn test_self_check() {
+    let segwit_transaction_hex = "02000000000101d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff028f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7fa9ce0f0000000000160014d850c02b89821f0f189ca7e81756c102241f7f4002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928bff62400";
+    let options = Options {
+        self_check: true,
+        ..Default::default()
+    };
+    let result = transaction_decoder_21::run_with_options(segwit_transaction_hex.to_string(), &options).unwrap();
+    assert_eq!(result, "ok: all 222 bytes round-trip");
+
+    let result = transaction_decoder_21::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &options).unwrap();
+    assert_eq!(result, "ok: all 371 bytes round-trip");
+
+    // a non-canonical input count (0xfd 0x0200 instead of 0x02) decodes fine but re-encodes differently
+    let non_canonical = format!("01000000fd0200{}", &LEGACY_TRANSACTION_HEX[10..]);
+    let result = transaction_decoder_21::run_with_options(non_canonical, &options).unwrap();
+    assert_eq!(result, "mismatch at byte 4: expected fd, re-encoded as 02");
+
+    let json = transaction_decoder_21::run(segwit_transaction_hex.to_string()).unwrap();
+    assert!(json.contains("\"transaction id\": \"17e1fcaae34575d0d1566c1ae64bf4c9f7b7b9df0ff505015d3eb72460fe3a61\""));
+}
+
+#[test]
+fn test_length_prefixed() {
+    // the legacy transaction is 371 (0x173) bytes
+    let options = Options {
+        length_prefixed: true,
+        ..Default::default()
+    };
+    let prefixed = format!("00000173{}", LEGACY_TRANSACTION_HEX);
+    assert_eq!(
+        transaction_decoder_21::run_with_options(prefixed, &options).unwrap(),
+        transaction_decoder_21::run(LEGACY_TRANSACTION_HEX.to_string()).unwrap()
+    );
+
+    let wrong_length = format!("00000172{}", LEGACY_TRANSACTION_HEX);
+    let err = transaction_decoder_21::run_with_options(wrong_length, &options).unwrap_err();
+    assert_eq!(err.to_string(), "parse failed: length prefix doesn't match the transaction length");
+
+    let err = transaction_decoder_21::run_with_options("0001".to_string(), &options).unwrap_err();
+    assert_eq!(err.to_string(), "parse failed: missing length prefix");
+}
+
+#[test]
+fn test_strip_prefix() {
+    let expected = transaction_decoder_21::run(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+
+    // split into dash separated groups of 8 digits, like some QR exports
+    let digits: Vec<char> = LEGACY_TRANSACTION_HEX.chars().collect();
+    let grouped: Vec<String> = digits.chunks(8).map(|group| group.iter().collect()).collect();
+    let options = Options {
+        strip_prefix: Some("rawtx:".to_string()),
+        ..Default::default()
+    };
+    let pasted = format!(" rawtx:{}\n", grouped.join("-"));
+    assert_eq!(transaction_decoder_21::run_with_options(pasted, &options).unwrap(), expected);
+
+    // the prefix is optional, but other characters aren't ignored
+    assert_eq!(transaction_decoder_21::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &options).unwrap(), expected);
+    let err = transaction_decoder_21::run_with_options(format!("tx:{}", LEGACY_TRANSACTION_HEX), &options).unwrap_err();
+    assert_eq!(err.to_string(), "Hex decoding error: Invalid character 't' at position 0");
+}
+
+#[test]
+fn test_oneline() {
+    let options = Options {
+        oneline: true,
+        ..Default::default()
+    };
+    let line = transaction_decoder_21::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &options).unwrap();
+    assert_eq!(
+        line,
+        "3c1804567a336c3944e30b3c2593970bfcbf5b15a40f4fc6b626a360ee0507f2 v1 2in/2out 371vB 0.03030587BTC"
+    );
+
+    // lowering the first input's sequence from 0xfffffffe to 0xfffffffd signals rbf
+    let replaceable = LEGACY_TRANSACTION_HEX.replacen("feffffff", "fdffffff", 1);
+    let line = transaction_decoder_21::run_with_options(replaceable, &options).unwrap();
+    assert!(line.ends_with(" v1 2in/2out 371vB 0.03030587BTC rbf"));
+
+    // the first output's amount raised to u64::MAX, so the total can't be summed
+    let overflowing = LEGACY_TRANSACTION_HEX.replacen("02ebb10f0000000000", "02ffffffffffffffff", 1);
+    let err = transaction_decoder_21::run_with_options(overflowing, &options).unwrap_err();
+    assert_eq!(err.to_string(), "output amounts add up to more than u64::MAX satoshis");
+}
+
+#[test]
+fn test_json_array() {
+    let options = Options {
+        json_array: true,
+        ..Default::default()
+    };
+    let input = format!("[\"{}\", \"0100\"]", LEGACY_TRANSACTION_HEX);
+    let json = transaction_decoder_21::run_with_options(input, &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let items = value.as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["transaction id"], "3c1804567a336c3944e30b3c2593970bfcbf5b15a40f4fc6b626a360ee0507f2");
+    assert_eq!(items[1]["index"], 1);
+    assert_eq!(items[1]["error"], "IO error: failed to fill whole buffer");
+
+    let err = transaction_decoder_21::run_with_options("[".to_string(), &options).unwrap_err();
+    assert!(err.to_string().starts_with("JSON parsing error"));
+}
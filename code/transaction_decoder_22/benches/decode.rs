@@ -0,0 +1,47 @@
+// Times decoding a 20,000 input transaction into the owned Transaction and the
+// borrowed TransactionRef, rendering its JSON, then hashing it. Run with
+// `cargo bench`, and again with `cargo bench --features fast-hash` to compare
+// the hashing backends. In a release build expect a few milliseconds per decode
+// and tens of milliseconds for the JSON; test_large_transaction_decode_time
+// fails if decoding and rendering together ever take seconds.
+use std::time::Instant;
+use transaction_decoder_22::prelude::*;
+use transaction_decoder_22::TransactionRef;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+const ITERATIONS: u32 = 20;
+
+fn main() {
+    let bytes = common::large_transaction(20_000);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let transaction = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap();
+        assert_eq!(transaction.inputs.len(), 20_000);
+    }
+    println!("Transaction::consensus_decode: {:?} per decode", start.elapsed() / ITERATIONS);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let transaction = TransactionRef::decode(&bytes).unwrap();
+        assert_eq!(transaction.inputs.len(), 20_000);
+    }
+    println!("TransactionRef::decode: {:?} per decode", start.elapsed() / ITERATIONS);
+
+    let raw_transaction_hex = hex::encode(&bytes);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        transaction_decoder_22::run(raw_transaction_hex.clone()).unwrap();
+    }
+    println!("run: {:?} per decode and render", start.elapsed() / ITERATIONS);
+
+    let transaction = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap();
+    let backend = if cfg!(feature = "fast-hash") { "sha2 asm" } else { "sha2" };
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        transaction.wtxid();
+    }
+    println!("Transaction::wtxid with {}: {:?} per hash", backend, start.elapsed() / ITERATIONS);
+}
@@ -0,0 +1,259 @@
+use crate::hashes;
+use crate::script::{self, ScriptType, OP_0, OP_1, OP_CHECKSIG, OP_DUP, OP_EQUAL, OP_EQUALVERIFY, OP_HASH160};
+use crate::transaction::Error;
+
+// Mainnet version bytes for base58 addresses
+const PUBKEY_ADDRESS_VERSION: u8 = 0x00;
+const SCRIPT_ADDRESS_VERSION: u8 = 0x05;
+
+// The scriptPubKey that a mainnet address pays to
+pub fn script_pubkey(address: &str) -> Result<Vec<u8>, Error> {
+    if address.to_lowercase().starts_with("bc1") {
+        let (version, program) = decode_segwit(address)?;
+        let mut script = vec![if version == 0 { OP_0 } else { OP_1 + version - 1 }];
+        script.push(program.len() as u8);
+        script.extend(program);
+        return Ok(script);
+    }
+
+    let (version, hash) = decode_base58check(address)?;
+    match version {
+        PUBKEY_ADDRESS_VERSION => {
+            let mut script = vec![OP_DUP, OP_HASH160, 0x14];
+            script.extend(hash);
+            script.extend([OP_EQUALVERIFY, OP_CHECKSIG]);
+            Ok(script)
+        }
+        SCRIPT_ADDRESS_VERSION => {
+            let mut script = vec![OP_HASH160, 0x14];
+            script.extend(hash);
+            script.push(OP_EQUAL);
+            Ok(script)
+        }
+        _ => Err(Error::ParseFailed("unsupported address version")),
+    }
+}
+
+// Which chain an address is for, since each has its own version bytes and bech32 prefix
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    fn pubkey_address_version(self) -> u8 {
+        match self {
+            Network::Mainnet => PUBKEY_ADDRESS_VERSION,
+            _ => 0x6f,
+        }
+    }
+
+    fn script_address_version(self) -> u8 {
+        match self {
+            Network::Mainnet => SCRIPT_ADDRESS_VERSION,
+            _ => 0xc4,
+        }
+    }
+
+    fn hrp(self) -> &'static str {
+        match self {
+            Network::Mainnet => MAINNET_HRP,
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+// The mainnet address a scriptPubKey pays to, if it's a standard type with an address
+pub fn from_script_pubkey(script: &[u8]) -> Option<String> {
+    from_script_pubkey_for(script, Network::Mainnet)
+}
+
+pub fn from_script_pubkey_for(script: &[u8], network: Network) -> Option<String> {
+    match script::script_type(script) {
+        ScriptType::P2pkh => Some(encode_base58check(network.pubkey_address_version(), &script[3..23])),
+        ScriptType::P2sh => Some(encode_base58check(network.script_address_version(), &script[2..22])),
+        ScriptType::P2wpkh | ScriptType::P2wsh | ScriptType::P2tr | ScriptType::WitnessUnknown => {
+            let version = if script[0] == OP_0 { 0 } else { script[0] - OP_1 + 1 };
+            Some(encode_segwit(network.hrp(), version, &script[2..]))
+        }
+        _ => None,
+    }
+}
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn encode_base58check(version: u8, payload: &[u8]) -> String {
+    let mut data = vec![version];
+    data.extend_from_slice(payload);
+    let checksum = hashes::sha256d(&data);
+    data.extend_from_slice(&checksum[..4]);
+
+    // repeatedly divide the big-endian number by 58, collecting the remainders
+    let mut digits: Vec<u8> = vec![];
+    for &byte in data.iter() {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    // each leading zero byte is written as a '1'
+    let leading_zeros = data.iter().take_while(|&&byte| byte == 0).count();
+    let mut address = "1".repeat(leading_zeros);
+    address.extend(digits.iter().rev().map(|&digit| BASE58_ALPHABET[digit as usize] as char));
+    address
+}
+
+// Returns the version byte and the 20 byte hash of a P2PKH or P2SH address
+fn decode_base58check(address: &str) -> Result<(u8, Vec<u8>), Error> {
+    // multiply the number decoded so far by 58 and add each digit in turn,
+    // keeping the bytes little-endian until the end
+    let mut bytes: Vec<u8> = vec![];
+    for c in address.bytes() {
+        let mut carry = BASE58_ALPHABET.iter()
+            .position(|&a| a == c)
+            .ok_or(Error::ParseFailed("invalid base58 character"))? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    // each leading '1' is a zero byte
+    let leading_zeros = address.bytes().take_while(|&c| c == b'1').count();
+    bytes.extend(std::iter::repeat_n(0, leading_zeros));
+    bytes.reverse();
+
+    if bytes.len() != 25 {
+        return Err(Error::ParseFailed("invalid address length"));
+    }
+    let (payload, checksum) = bytes.split_at(21);
+    if hashes::sha256d(payload)[..4] != *checksum {
+        return Err(Error::ParseFailed("invalid address checksum"));
+    }
+    Ok((payload[0], payload[1..].to_vec()))
+}
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+// BIP173 uses a checksum constant of 1 for version 0, BIP350 changed it for later versions
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk: u32 = 1;
+    for &value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn bech32_hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 31));
+    values
+}
+
+const MAINNET_HRP: &str = "bc";
+
+fn encode_segwit(hrp: &str, version: u8, program: &[u8]) -> String {
+    // regroup the program's bytes into 5 bit values, padding the last one with zeros
+    let mut data = vec![version];
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for &byte in program {
+        acc = ((acc << 8) | byte as u32) & 0xfff;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            data.push(((acc >> bits) & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        data.push(((acc << (5 - bits)) & 31) as u8);
+    }
+
+    let mut values = bech32_hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0; 6]);
+    let constant = if version == 0 { BECH32_CONST } else { BECH32M_CONST };
+    let polymod = bech32_polymod(&values) ^ constant;
+    data.extend((0..6).map(|i| ((polymod >> (5 * (5 - i))) & 31) as u8));
+
+    let mut address = format!("{}1", hrp);
+    address.extend(data.iter().map(|&value| BECH32_CHARSET[value as usize] as char));
+    address
+}
+
+// Returns the witness version and program of a bech32 or bech32m address
+fn decode_segwit(address: &str) -> Result<(u8, Vec<u8>), Error> {
+    if address.to_lowercase() != address && address.to_uppercase() != address {
+        return Err(Error::ParseFailed("mixed case address"));
+    }
+    let address = address.to_lowercase();
+    let (hrp, data) = address.rsplit_once('1').ok_or(Error::ParseFailed("invalid bech32 address"))?;
+    if hrp != MAINNET_HRP || data.len() < 7 {
+        return Err(Error::ParseFailed("invalid bech32 address"));
+    }
+    let data: Vec<u8> = data.bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|&a| a == c).map(|value| value as u8))
+        .collect::<Option<_>>()
+        .ok_or(Error::ParseFailed("invalid bech32 character"))?;
+
+    let mut values = bech32_hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    let version = data[0];
+    let expected = if version == 0 { BECH32_CONST } else { BECH32M_CONST };
+    if bech32_polymod(&values) != expected {
+        return Err(Error::ParseFailed("invalid address checksum"));
+    }
+
+    // regroup the 5 bit values (without the version and checksum) into bytes,
+    // where any leftover padding bits must be zero
+    let mut program = vec![];
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for &value in &data[1..data.len() - 6] {
+        acc = ((acc << 5) | value as u32) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            program.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        return Err(Error::ParseFailed("invalid bech32 padding"));
+    }
+
+    let valid_length = match version {
+        0 => program.len() == 20 || program.len() == 32,
+        1..=16 => (2..=40).contains(&program.len()),
+        _ => false,
+    };
+    if !valid_length {
+        return Err(Error::ParseFailed("invalid witness program"));
+    }
+    Ok((version, program))
+}
@@ -0,0 +1,183 @@
+use crate::transaction::{
+    Amount, CompactSize, Decodable, Error, OutPoint, Version, MIN_TXIN_SIZE, MIN_TXOUT_SIZE, SEGWIT_FLAG_OFFSET,
+};
+
+// A decoded transaction whose scripts and witness items are slices of the bytes it
+// was decoded from rather than copies. Decoding only allocates the input and output
+// lists, which makes it cheaper for scanning many or very large transactions.
+// Use Transaction when the decoded transaction needs to outlive the bytes.
+#[derive(Debug, Clone)]
+pub struct TransactionRef<'a> {
+    pub version: Version,
+    pub inputs: Vec<TxInRef<'a>>,
+    pub outputs: Vec<TxOutRef<'a>>,
+    pub lock_time: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TxInRef<'a> {
+    pub previous_output: OutPoint,
+    pub script_sig: &'a [u8],
+    pub sequence: u32,
+    pub witness: Vec<&'a [u8]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TxOutRef<'a> {
+    pub amount: Amount,
+    pub script_pubkey: &'a [u8],
+}
+
+impl<'a> TransactionRef<'a> {
+    pub fn decode(bytes: &'a [u8]) -> Result<TransactionRef<'a>, Error> {
+        let r = &mut &bytes[..];
+        let version = Version::consensus_decode(r)?;
+
+        let mut input_count = read_count(r, "input count")?;
+        let mut segwit = false;
+        if input_count == 0 {
+            match take(r, 1, "segwit flag")?[0] {
+                1 => segwit = true,
+                flag => {
+                    return Err(Error::UnsupportedSegwitFlag { flag, offset: SEGWIT_FLAG_OFFSET, inputs: None, outputs: None })
+                }
+            }
+            input_count = read_count(r, "input count")?;
+        }
+
+        // the count can't be trusted, but the bytes left bound how many there can be
+        let mut inputs = Vec::with_capacity((input_count as usize).min(r.len() / MIN_TXIN_SIZE));
+        for _ in 0..input_count {
+            inputs.push(TxInRef {
+                previous_output: OutPoint::consensus_decode(r).map_err(|_| Error::UnexpectedEof("previous output"))?,
+                script_sig: take_var(r, "scriptSig")?,
+                sequence: read_u32(r, "sequence")?,
+                witness: vec![],
+            });
+        }
+
+        let output_count = read_count(r, "output count")?;
+        let mut outputs = Vec::with_capacity((output_count as usize).min(r.len() / MIN_TXOUT_SIZE));
+        for _ in 0..output_count {
+            let amount = u64::from_le_bytes(take(r, 8, "amount")?.try_into().expect("took 8 bytes"));
+            outputs.push(TxOutRef {
+                amount: Amount::from_sat(amount),
+                script_pubkey: take_var(r, "scriptPubKey")?,
+            });
+        }
+
+        if segwit {
+            for input in inputs.iter_mut() {
+                let item_count = read_count(r, "witness")?;
+                for _ in 0..item_count {
+                    input.witness.push(take_var(r, "witness")?);
+                }
+            }
+            if !inputs.is_empty() && inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(Error::ParseFailed("witness flag set but no witnesses present"));
+            }
+        }
+
+        Ok(TransactionRef {
+            version,
+            inputs,
+            outputs,
+            lock_time: read_u32(r, "locktime")?,
+        })
+    }
+}
+
+// Splits the next len bytes off the front without copying them
+fn take<'a>(r: &mut &'a [u8], len: usize, field: &'static str) -> Result<&'a [u8], Error> {
+    if len > r.len() {
+        return Err(Error::UnexpectedEof(field));
+    }
+    let (bytes, rest) = r.split_at(len);
+    *r = rest;
+    Ok(bytes)
+}
+
+// A CompactSize length followed by that many bytes
+fn take_var<'a>(r: &mut &'a [u8], field: &'static str) -> Result<&'a [u8], Error> {
+    let len = read_count(r, field)?;
+    take(r, len as usize, field)
+}
+
+fn read_count(r: &mut &[u8], field: &'static str) -> Result<u64, Error> {
+    Ok(CompactSize::consensus_decode(r).map_err(|_| Error::UnexpectedEof(field))?.0)
+}
+
+fn read_u32(r: &mut &[u8], field: &'static str) -> Result<u32, Error> {
+    Ok(u32::from_le_bytes(take(r, 4, field)?.try_into().expect("took 4 bytes")))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::TransactionRef;
+    use crate::test_vectors::SEGWIT_TRANSACTION_HEX;
+    use crate::transaction::{Decodable, Error, Transaction};
+
+    #[test]
+    fn test_borrows_from_the_buffer() {
+        let bytes = hex::decode(SEGWIT_TRANSACTION_HEX).unwrap();
+        let transaction = TransactionRef::decode(&bytes).unwrap();
+        let buffer = bytes.as_ptr_range();
+
+        for output in transaction.outputs.iter() {
+            assert!(buffer.contains(&output.script_pubkey.as_ptr()));
+        }
+        // the first output's script follows its amount and length byte, and the
+        // output itself starts at byte 49 after the input and the output count
+        assert_eq!(transaction.outputs[0].script_pubkey.as_ptr(), bytes[58..].as_ptr());
+        for item in transaction.inputs[0].witness.iter() {
+            assert!(buffer.contains(&item.as_ptr()));
+        }
+    }
+
+    #[test]
+    fn test_matches_owned_decode() {
+        let bytes = hex::decode(SEGWIT_TRANSACTION_HEX).unwrap();
+        let borrowed = TransactionRef::decode(&bytes).unwrap();
+        let owned = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(borrowed.version.0, owned.version.0);
+        assert_eq!(borrowed.lock_time, owned.lock_time);
+        assert_eq!(borrowed.inputs.len(), owned.inputs.len());
+        for (input, owned_input) in borrowed.inputs.iter().zip(owned.inputs.iter()) {
+            assert_eq!(input.previous_output, owned_input.previous_output);
+            assert_eq!(hex::encode(input.script_sig), owned_input.script_sig);
+            assert_eq!(input.sequence, owned_input.sequence);
+            assert_eq!(input.witness, owned_input.witness.items());
+        }
+        assert_eq!(borrowed.outputs.len(), owned.outputs.len());
+        for (output, owned_output) in borrowed.outputs.iter().zip(owned.outputs.iter()) {
+            assert_eq!(output.amount, owned_output.amount);
+            assert_eq!(hex::encode(output.script_pubkey), owned_output.script_pubkey);
+        }
+    }
+
+    #[test]
+    fn test_huge_count() {
+        // an input count of 2^64 - 1 and of 0xffffff00, with nothing after them
+        for raw_transaction_hex in ["01000000ffffffffffffffffff", "01000000fe00ffffff"] {
+            let bytes = hex::decode(raw_transaction_hex).unwrap();
+            let err = TransactionRef::decode(&bytes).unwrap_err();
+            assert!(matches!(err, Error::UnexpectedEof("previous output")));
+            let err = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap_err();
+            assert!(matches!(err, Error::UnexpectedEof("previous output")));
+        }
+
+        // a scriptSig claiming to be 2^32 - 1 bytes long
+        let bytes = hex::decode(format!("0100000001{}00000000feffffffff", "11".repeat(32))).unwrap();
+        assert!(matches!(TransactionRef::decode(&bytes).unwrap_err(), Error::UnexpectedEof("scriptSig")));
+        let err = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap_err();
+        assert!(matches!(err, Error::UnexpectedEof("scriptSig")));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = hex::decode(&SEGWIT_TRANSACTION_HEX[..SEGWIT_TRANSACTION_HEX.len() - 4]).unwrap();
+        let err = TransactionRef::decode(&bytes).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected end of input while reading locktime");
+    }
+}
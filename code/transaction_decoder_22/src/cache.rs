@@ -0,0 +1,85 @@
+use std::num::NonZeroUsize;
+use lru::LruCache;
+use crate::transaction::{Decodable, Error, Transaction};
+
+// Keeps the most recently decoded transactions around so decoding the same hex
+// again (e.g. when re-scanning a mempool) doesn't have to parse it a second time.
+// Entries are keyed by the lowercased hex rather than the txid, since transactions
+// with different witnesses share a txid but decode to different transactions.
+pub struct DecodeCache {
+    entries: LruCache<String, Transaction>,
+    decodes: usize,
+}
+
+impl DecodeCache {
+    // A capacity of 0 is treated as 1
+    pub fn new(capacity: usize) -> Self {
+        DecodeCache {
+            entries: LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN)),
+            decodes: 0,
+        }
+    }
+
+    pub fn get_or_decode(&mut self, raw_transaction_hex: &str) -> Result<&Transaction, Error> {
+        let key = raw_transaction_hex.to_ascii_lowercase();
+
+        if !self.entries.contains(&key) {
+            let bytes = hex::decode(&key)
+                .map_err(|_| Error::ParseFailed("invalid transaction hex"))?;
+            let transaction = Transaction::consensus_decode(&mut bytes.as_slice())?;
+            self.decodes += 1;
+            self.entries.put(key.clone(), transaction);
+        }
+        Ok(self.entries.get(&key).expect("transaction was just cached"))
+    }
+
+    // How many times the parser has actually been run
+    pub fn decode_count(&self) -> usize {
+        self.decodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::DecodeCache;
+    use crate::test_vectors::{LEGACY_TRANSACTION_HEX, SEGWIT_TRANSACTION_HEX};
+
+    #[test]
+    fn test_second_decode_is_cached() {
+        let mut cache = DecodeCache::new(1);
+        let txid = cache.get_or_decode(LEGACY_TRANSACTION_HEX).unwrap().txid();
+        assert_eq!(cache.decode_count(), 1);
+
+        assert_eq!(cache.get_or_decode(LEGACY_TRANSACTION_HEX).unwrap().txid(), txid);
+        assert_eq!(cache.decode_count(), 1);
+        assert_eq!(cache.len(), 1);
+
+        // the same transaction in uppercase hex shares the entry
+        assert_eq!(cache.get_or_decode(&LEGACY_TRANSACTION_HEX.to_uppercase()).unwrap().txid(), txid);
+        assert_eq!(cache.decode_count(), 1);
+        assert_eq!(cache.len(), 1);
+
+        // with room for only one transaction, decoding another evicts the first
+        cache.get_or_decode(SEGWIT_TRANSACTION_HEX).unwrap();
+        assert_eq!(cache.decode_count(), 2);
+        cache.get_or_decode(LEGACY_TRANSACTION_HEX).unwrap();
+        assert_eq!(cache.decode_count(), 3);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_hex_is_not_cached() {
+        let mut cache = DecodeCache::new(4);
+        assert!(cache.get_or_decode("zz").is_err());
+        assert!(cache.is_empty());
+        assert_eq!(cache.decode_count(), 0);
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::{HashMap, HashSet};
+use crate::transaction::{Amount, AmountDelta, OutPoint, Transaction, TxOut};
+
+// A single difference between two transactions, such as an original and its replacement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldDiff {
+    Version { old: u32, new: u32 },
+    InputAdded(OutPoint),
+    InputRemoved(OutPoint),
+    // inputs are matched by the outpoint they spend, not their position
+    Sequence { outpoint: OutPoint, old: u32, new: u32 },
+    OutputAmount { index: usize, old: Amount, new: Amount },
+    OutputScript { index: usize, old: String, new: String },
+    OutputAdded { index: usize, output: TxOut },
+    OutputRemoved { index: usize, output: TxOut },
+    // The change in fee going from the first transaction to the second. Without the
+    // prevout amounts this is only known when both spend exactly the same outpoints,
+    // in which case it's the drop in the total output value.
+    Fee(AmountDelta),
+    LockTime { old: u32, new: u32 },
+}
+
+// Whether the transactions spend at least one of the same outpoints, so only one
+// of them can confirm and either could replace the other. Coinbase inputs don't
+// spend a real outpoint, so they never conflict.
+pub fn conflicts(a: &Transaction, b: &Transaction) -> bool {
+    let spent: HashSet<OutPoint> = a.inputs.iter()
+        .filter(|input| !input.is_coinbase())
+        .map(|input| input.previous_output)
+        .collect();
+    b.inputs.iter().any(|input| !input.is_coinbase() && spent.contains(&input.previous_output))
+}
+
+impl Transaction {
+    // Differences are listed in the order the fields appear in the transaction,
+    // so diffing the same pair always gives the same result
+    pub fn diff(&self, other: &Transaction) -> Vec<FieldDiff> {
+        let mut diffs = vec![];
+        if self.version.0 != other.version.0 {
+            diffs.push(FieldDiff::Version { old: self.version.0, new: other.version.0 });
+        }
+
+        let old_sequences: HashMap<OutPoint, u32> = self.inputs.iter()
+            .map(|input| (input.previous_output, input.sequence))
+            .collect();
+        let new_sequences: HashMap<OutPoint, u32> = other.inputs.iter()
+            .map(|input| (input.previous_output, input.sequence))
+            .collect();
+        for input in self.inputs.iter() {
+            match new_sequences.get(&input.previous_output) {
+                None => diffs.push(FieldDiff::InputRemoved(input.previous_output)),
+                Some(&new) if new != input.sequence => diffs.push(FieldDiff::Sequence {
+                    outpoint: input.previous_output,
+                    old: input.sequence,
+                    new,
+                }),
+                Some(_) => {}
+            }
+        }
+        for input in other.inputs.iter() {
+            if !old_sequences.contains_key(&input.previous_output) {
+                diffs.push(FieldDiff::InputAdded(input.previous_output));
+            }
+        }
+
+        for index in 0..self.outputs.len().max(other.outputs.len()) {
+            match (self.outputs.get(index), other.outputs.get(index)) {
+                (Some(old), Some(new)) => {
+                    if old.amount != new.amount {
+                        diffs.push(FieldDiff::OutputAmount { index, old: old.amount, new: new.amount });
+                    }
+                    if !old.script_eq(&new.script_pubkey) {
+                        diffs.push(FieldDiff::OutputScript {
+                            index,
+                            old: old.script_pubkey.clone(),
+                            new: new.script_pubkey.clone(),
+                        });
+                    }
+                }
+                (Some(old), None) => diffs.push(FieldDiff::OutputRemoved { index, output: old.clone() }),
+                (None, Some(new)) => diffs.push(FieldDiff::OutputAdded { index, output: new.clone() }),
+                (None, None) => unreachable!(),
+            }
+        }
+
+        let same_inputs = self.inputs.len() == other.inputs.len()
+            && self.inputs.iter().all(|input| new_sequences.contains_key(&input.previous_output));
+        // totals that overflow aren't real amounts, so there's no fee change to report
+        if let (true, Ok(old_total), Ok(new_total)) = (same_inputs, self.created_outputs_total(), other.created_outputs_total()) {
+            match AmountDelta::from_diff(old_total, new_total) {
+                Some(fee_delta) if fee_delta.0 != 0 => diffs.push(FieldDiff::Fee(fee_delta)),
+                _ => {}
+            }
+        }
+
+        if self.lock_time != other.lock_time {
+            diffs.push(FieldDiff::LockTime { old: self.lock_time, new: other.lock_time });
+        }
+        diffs
+    }
+}
@@ -0,0 +1,144 @@
+use std::ops::Range;
+use crate::address;
+use crate::script::{self, ScriptType};
+use crate::transaction::{Error, SpendType, Transaction, TxIn, TxOut};
+
+// Walks through the raw transaction in order, describing what each part of it is
+pub fn explain(bytes: &[u8]) -> Result<String, Error> {
+    let (transaction, spans) = Transaction::decode_with_spans(bytes)?;
+    let hex = |span: &Range<usize>| hex::encode(&bytes[span.clone()]);
+
+    let mut sentences = vec![format!(
+        "The first 4 bytes `{}` are the version ({}, little-endian).",
+        hex(&spans.version), transaction.version.0,
+    )];
+    if let Some(marker_and_flag) = &spans.marker_and_flag {
+        sentences.push(format!(
+            "The next 2 bytes `{}` are the segwit marker and flag, so witnesses follow the outputs.",
+            hex(marker_and_flag),
+        ));
+    }
+
+    sentences.push(format!(
+        "The next {} `{}` is the input count ({}).",
+        byte_count(&spans.input_count), hex(&spans.input_count), transaction.inputs.len(),
+    ));
+    let input_weights = transaction.input_weights();
+    for (i, (input, span)) in transaction.inputs.iter().zip(spans.inputs.iter()).enumerate() {
+        // the witness bytes come later but are counted with the input they belong to
+        let with_witness = if input.witness.items().is_empty() { "" } else { " including its witness" };
+        // the sequence is the last field of the input
+        let sequence = hex(&(span.end - 4..span.end));
+        let label = if input.sequence == 0xffffffff { "final" } else { "non-final" };
+        sentences.push(format!(
+            "Input {} is the next {}, adding {} weight units{}. It spends {}, {}. Its last 4 bytes `{}` are the sequence, {}.",
+            i, byte_count(span), input_weights[i], with_witness, input.previous_output, describe_input(input),
+            sequence, describe_u32(input.sequence, label),
+        ));
+    }
+
+    sentences.push(format!(
+        "The next {} `{}` is the output count ({}).",
+        byte_count(&spans.output_count), hex(&spans.output_count), transaction.outputs.len(),
+    ));
+    for (i, (output, span)) in transaction.outputs.iter().zip(spans.outputs.iter()).enumerate() {
+        sentences.push(format!(
+            "Output {} is the next {}, adding {} weight units. {}",
+            i, byte_count(span), output.weight(), describe_output(output),
+        ));
+    }
+
+    for (i, (input, span)) in transaction.inputs.iter().zip(spans.witnesses.iter()).enumerate() {
+        sentences.push(format!(
+            "The witness for input {} is the next {}, holding {} item(s).",
+            i, byte_count(span), input.witness.items().len(),
+        ));
+    }
+
+    sentences.push(format!(
+        "The last 4 bytes `{}` are the locktime, {}.",
+        hex(&spans.locktime), describe_u32(transaction.lock_time, locktime_label(&transaction)),
+    ));
+    if let Some(sentence) = describe_locktime(&transaction) {
+        sentences.push(sentence);
+    }
+    Ok(sentences.join("\n"))
+}
+
+// Spells out how the 4 little-endian bytes of a field are read, since the
+// reversed bytes are easy to misread
+fn describe_u32(value: u32, label: &str) -> String {
+    format!("which read little-endian are 0x{:08x} or {} ({})", value, value, label)
+}
+
+fn locktime_label(transaction: &Transaction) -> &'static str {
+    if transaction.lock_time == 0 {
+        "no locktime"
+    } else if transaction.locktime_is_timestamp() {
+        "a unix timestamp"
+    } else {
+        "a block height"
+    }
+}
+
+// A non-zero locktime only counts if at least one input's sequence isn't final,
+// which is easy to miss since the two fields are far apart
+fn describe_locktime(transaction: &Transaction) -> Option<String> {
+    if transaction.lock_time == 0 {
+        return None;
+    }
+    let non_final: Vec<String> = transaction.inputs.iter().enumerate()
+        .filter(|(_, input)| input.sequence != 0xffffffff)
+        .map(|(i, _)| i.to_string())
+        .collect();
+    if non_final.is_empty() {
+        return Some(
+            "Every input's sequence is final (0xffffffff), so the locktime is not enforced and the transaction can be mined at any time.".to_string()
+        );
+    }
+    let inputs = match non_final.as_slice() {
+        [i] => format!("Input {} has a non-final sequence", i),
+        _ => format!("Inputs {} have non-final sequences", non_final.join(", ")),
+    };
+    let until = if transaction.locktime_is_timestamp() {
+        format!("the median time of the last 11 blocks is past {} (a unix timestamp)", transaction.lock_time)
+    } else {
+        format!("block height {}", transaction.lock_time as u64 + 1)
+    };
+    Some(format!("{}, which enables the locktime: the transaction can't be mined until {}.", inputs, until))
+}
+
+fn byte_count(span: &Range<usize>) -> String {
+    match span.len() {
+        1 => "byte".to_string(),
+        len => format!("{} bytes", len),
+    }
+}
+
+fn describe_input(input: &TxIn) -> &'static str {
+    match input.spend_type() {
+        SpendType::Coinbase => "which means it's a coinbase input creating new coins",
+        SpendType::Legacy => "as a legacy spend with everything in the scriptSig",
+        SpendType::NestedSegwit => "as a nested segwit spend, with a witness program in the scriptSig and the signatures in the witness",
+        SpendType::NativeSegwit => "as a native segwit spend with the signatures in the witness",
+        SpendType::TaprootKeyPath => "as a taproot key path spend",
+        SpendType::TaprootScriptPath => "as a taproot script path spend",
+    }
+}
+
+fn describe_output(output: &TxOut) -> String {
+    let script_pubkey = hex::decode(&output.script_pubkey).unwrap_or_default();
+    let btc = output.value_btc();
+    let address = address::from_script_pubkey(&script_pubkey).unwrap_or_default();
+    match script::script_type(&script_pubkey) {
+        ScriptType::P2pkh => format!("This output pays {} BTC to a P2PKH address {} (hash of a single public key).", btc, address),
+        ScriptType::P2sh => format!("This output pays {} BTC to a P2SH address {} (hash of a redeem script).", btc, address),
+        ScriptType::P2wpkh => format!("This output pays {} BTC to a P2WPKH address {} (witness program for a single public key hash).", btc, address),
+        ScriptType::P2wsh => format!("This output pays {} BTC to a P2WSH address {} (witness program for a script hash).", btc, address),
+        ScriptType::P2tr => format!("This output pays {} BTC to a P2TR address {} (taproot output key).", btc, address),
+        ScriptType::WitnessUnknown => format!("This output pays {} BTC to a future segwit version address {}.", btc, address),
+        ScriptType::P2pk => format!("This output pays {} BTC directly to a public key (P2PK).", btc),
+        ScriptType::OpReturn => format!("This output is an OP_RETURN data carrier worth {} BTC, which can never be spent.", btc),
+        ScriptType::NonStandard => format!("This output locks {} BTC with a non-standard script.", btc),
+    }
+}
@@ -0,0 +1,116 @@
+use std::io::{Read, Result, Write};
+
+// Wraps a reader and keeps track of how many bytes have been read through it
+pub struct CountingReader<R: Read> {
+    inner: R,
+    position: usize,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        CountingReader { inner, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.position += len;
+        Ok(len)
+    }
+}
+
+// Hex encodes everything written to it into a string, so encoding to hex doesn't
+// need the bytes collected into a Vec first
+#[derive(Debug, Default)]
+pub struct HexWriter {
+    hex: String,
+}
+
+impl HexWriter {
+    pub fn new() -> Self {
+        HexWriter::default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.hex
+    }
+
+    pub fn into_string(self) -> String {
+        self.hex
+    }
+}
+
+impl Write for HexWriter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        self.hex.reserve(buf.len() * 2);
+        for byte in buf {
+            self.hex.push(DIGITS[(byte >> 4) as usize] as char);
+            self.hex.push(DIGITS[(byte & 0x0f) as usize] as char);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{CountingReader, HexWriter};
+    use crate::test_vectors::SEGWIT_TRANSACTION_HEX;
+    use crate::transaction::{Decodable, Encodable, Transaction};
+    use std::io::Read;
+
+    #[test]
+    fn test_position() {
+        let bytes = [1_u8, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let mut reader = CountingReader::new(bytes.as_slice());
+        assert_eq!(reader.position(), 0);
+
+        let mut buffer = [0; 3];
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(reader.position(), 3);
+
+        let mut buffer = [0; 2];
+        let len = reader.read(&mut buffer).unwrap();
+        assert_eq!(len, 2);
+        assert_eq!(reader.position(), 5);
+
+        let mut buffer = [0; 4];
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, [6, 7, 8, 9]);
+        assert_eq!(reader.position(), 9);
+
+        // a short read only counts the bytes actually read
+        let mut buffer = [0; 4];
+        let len = reader.read(&mut buffer).unwrap();
+        assert_eq!(len, 1);
+        assert_eq!(reader.position(), 10);
+
+        let mut buffer = [0; 1];
+        assert!(reader.read_exact(&mut buffer).is_err());
+        assert_eq!(reader.position(), 10);
+    }
+
+    #[test]
+    fn test_hex_writer() {
+        let transaction = Transaction::consensus_decode(&mut hex::decode(SEGWIT_TRANSACTION_HEX).unwrap().as_slice()).unwrap();
+
+        let mut writer = HexWriter::new();
+        let len = transaction.consensus_encode(&mut writer).unwrap();
+        assert_eq!(len, SEGWIT_TRANSACTION_HEX.len() / 2);
+        assert_eq!(writer.as_str(), hex::encode(transaction.to_bytes()));
+        assert_eq!(writer.into_string(), SEGWIT_TRANSACTION_HEX);
+    }
+}
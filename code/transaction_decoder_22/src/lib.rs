@@ -1,23 +1,164 @@
-mod transaction;
-use self::transaction::{Decodable, Transaction};
+pub mod address;
+mod borrowed;
+#[cfg(feature = "decode-cache")]
+mod cache;
+mod diff;
+mod explain;
+mod hashes;
+pub mod io;
+mod pretty;
+pub mod psbt;
+pub mod script;
+mod sighash;
+mod signature;
+pub mod transaction;
+mod view;
+// The sample transactions from the integration tests, shared by the unit tests
+#[cfg(test)]
+mod test_vectors {
+    pub const LEGACY_TRANSACTION_HEX: &str = "010000000242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffff75c87cc5f3150eefc1c04c0246e7e0b370e64b17d6226c44b333a6f4ca14b49c000000006b483045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c47012103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abffeffffff02ebb10f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac508c1e000000000017a91476c0c8f2fc403c5edaea365f6a284317b9cdf7258700000000";
+    pub const SEGWIT_TRANSACTION_HEX: &str = "02000000000101d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff028f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7fa9ce0f0000000000160014d850c02b89821f0f189ca7e81756c102241f7f4002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928bff62400";
+}
+use self::script::Script;
+use self::transaction::{Decodable, Encodable, VerboseWitness};
+pub use self::transaction::{Amount, AmountDelta, FeeBucket, OutPoint, Transaction, TxSpans, Txid};
+#[cfg(feature = "decode-cache")]
+pub use self::cache::DecodeCache;
+pub use self::borrowed::{TransactionRef, TxInRef, TxOutRef};
+pub use self::address::Network;
+pub use self::diff::{conflicts, FieldDiff};
+pub use self::pretty::{AmountUnit, KeyStyle};
+pub use self::view::{TransactionFlags, TransactionView, TxInView, TxOutView};
+use serde::Serialize;
 use std::error::Error;
-use clap::{arg, value_parser, Command};
 
-pub fn get_arg() -> String {
+/// The types needed to decode and encode transactions, so library users can
+/// import them all at once.
+///
+/// ```
+/// use transaction_decoder_22::prelude::*;
+///
+/// let bytes = hex::decode("02000000000101d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff028f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7fa9ce0f0000000000160014d850c02b89821f0f189ca7e81756c102241f7f4002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928bff62400").unwrap();
+/// let transaction = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap();
+/// assert_eq!(transaction.txid().to_string(), "17e1fcaae34575d0d1566c1ae64bf4c9f7b7b9df0ff505015d3eb72460fe3a61");
+/// assert_eq!(transaction.outputs[0].amount, Amount::from_sat(2_034_575));
+///
+/// let mut encoded = Vec::new();
+/// transaction.consensus_encode(&mut encoded).unwrap();
+/// assert_eq!(encoded, bytes);
+/// ```
+pub mod prelude {
+    pub use crate::transaction::{Amount, Decodable, Encodable, Transaction, TxIn, TxOut, Txid, Witness};
+}
+use clap::{arg, value_parser, ArgAction, Command};
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub include_hex: bool,
+    pub outpoints: bool,
+    pub address_to_script: Option<String>,
+    pub explain: bool,
+    pub resolve_time: bool,
+    pub assume_legacy: bool,
+    pub verbose_witness: bool,
+    pub trim_amounts: bool,
+    pub scripts: bool,
+    pub key_style: KeyStyle,
+    pub amount_unit: AmountUnit,
+    pub tolerate_trailing: bool,
+}
+
+pub fn get_args() -> (String, Options) {
     let matches = Command::new("Bitcoin Transaction Decoder")
         .version("1.0")
         .about("Decodes a raw transaction")
         .arg(
             arg!([RAW_TRANSACTION])
                 .value_parser(value_parser!(String))
-                .required(true)
+                .required_unless_present("address-to-script")
+        )
+        .arg(
+            arg!(--"include-hex" "Include the re-encoded transaction as a hex field")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--outpoints "Print the txid:vout of each spent output, one per line")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--explain "Describe each part of the transaction in plain English")
+                .action(ArgAction::SetTrue)
+                // the walkthrough follows the segwit layout and ends at the locktime
+                .conflicts_with_all(["assume-legacy", "tolerate-trailing"])
+        )
+        .arg(
+            arg!(--"resolve-time" "Include the locktime as a date when it's a timestamp")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"verbose-witness" "Include each input's witness items with their sizes")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--scripts "Only print each input's and output's script as hex and asm, and the witnesses")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"trim-amounts" "Write amounts as exact decimals without trailing zeros")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--amounts <UNIT> "Write amounts in btc (the default), sat, or both as an object")
+                .value_parser(["btc", "sat", "both"])
+        )
+        .arg(
+            arg!(--"key-style" <STYLE> "Name keys like Bitcoin Core (core) or consistently in snake_case (snake)")
+                .value_parser(["core", "snake"])
+        )
+        .arg(
+            arg!(--"tolerate-trailing" "Report any bytes after the locktime instead of rejecting them")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"assume-legacy" "Never treat a zero input count as the segwit marker")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"address-to-script" <ADDRESS> "Print the scriptPubKey hex that an address pays to")
+                .value_parser(value_parser!(String))
         )
         .get_matches();
 
-    matches
+    // not needed when converting an address
+    let raw_transaction = matches
         .get_one::<String>("RAW_TRANSACTION")
         .cloned()
-        .expect("raw transaction is required")
+        .unwrap_or_default();
+
+    let options = Options {
+        include_hex: matches.get_flag("include-hex"),
+        outpoints: matches.get_flag("outpoints"),
+        address_to_script: matches.get_one::<String>("address-to-script").cloned(),
+        explain: matches.get_flag("explain"),
+        resolve_time: matches.get_flag("resolve-time"),
+        assume_legacy: matches.get_flag("assume-legacy"),
+        verbose_witness: matches.get_flag("verbose-witness"),
+        trim_amounts: matches.get_flag("trim-amounts"),
+        scripts: matches.get_flag("scripts"),
+        key_style: match matches.get_one::<String>("key-style").map(String::as_str) {
+            Some("core") => KeyStyle::Core,
+            Some("snake") => KeyStyle::Snake,
+            _ => KeyStyle::Mixed,
+        },
+        amount_unit: match matches.get_one::<String>("amounts").map(String::as_str) {
+            Some("sat") => AmountUnit::Sat,
+            Some("both") => AmountUnit::Both,
+            _ => AmountUnit::Btc,
+        },
+        tolerate_trailing: matches.get_flag("tolerate-trailing"),
+    };
+
+    (raw_transaction, options)
 }
 
 pub fn decode(raw_transaction_hex: String) -> Result<Transaction, Box<dyn Error>> {
@@ -26,7 +167,199 @@ pub fn decode(raw_transaction_hex: String) -> Result<Transaction, Box<dyn Error>
     Ok(Transaction::consensus_decode(&mut bytes_slice)?)
 }
 
+// Decodes the transaction at the start of the bytes and returns whatever is
+// left after its locktime. An unsupported segwit flag is reported with the
+// input and output counts that follow it.
+fn decode_prefix(transaction_bytes: &[u8], assume_legacy: bool) -> Result<(Transaction, &[u8]), Box<dyn Error>> {
+    let mut bytes_slice = transaction_bytes;
+    let result = if assume_legacy {
+        Transaction::consensus_decode_legacy(&mut bytes_slice)
+    } else {
+        Transaction::consensus_decode(&mut bytes_slice)
+    };
+    let transaction = match result {
+        Err(transaction::Error::UnsupportedSegwitFlag { flag, offset, .. }) => {
+            let (inputs, outputs) = transaction::infer_counts_after_flag(&transaction_bytes[offset + 1..]);
+            return Err(transaction::Error::UnsupportedSegwitFlag { flag, offset, inputs, outputs }.into());
+        }
+        result => result?,
+    };
+    Ok((transaction, bytes_slice))
+}
+
+fn trailing_bytes_error(transaction_bytes: &[u8], trailing: &[u8]) -> Box<dyn Error> {
+    format!(
+        "{} trailing bytes after the transaction at offset {}",
+        trailing.len(), transaction_bytes.len() - trailing.len(),
+    ).into()
+}
+
+// Like decode, but also rejects any bytes left over after the locktime, which
+// decode ignores
+pub fn decode_strict(raw_transaction_hex: String) -> Result<Transaction, Box<dyn Error>> {
+    let transaction_bytes = hex::decode(raw_transaction_hex).map_err(|e| format!("Hex decoding error: {}", e))?;
+    let (transaction, trailing) = decode_prefix(&transaction_bytes, false)?;
+    if !trailing.is_empty() {
+        return Err(trailing_bytes_error(&transaction_bytes, trailing));
+    }
+    Ok(transaction)
+}
+
+// Decodes with the pre-segwit layout, even if the input count is zero
+pub fn decode_legacy(raw_transaction_hex: String) -> Result<Transaction, Box<dyn Error>> {
+    let transaction_bytes = hex::decode(raw_transaction_hex).map_err(|e| format!("Hex decoding error: {}", e))?;
+    let mut bytes_slice = transaction_bytes.as_slice();
+    Ok(Transaction::consensus_decode_legacy(&mut bytes_slice)?)
+}
+
+// The transaction's JSON plus any extra fields asked for in the options
+
+#[derive(Serialize)]
+struct TransactionOutput<'a> {
+    #[serde(flatten)]
+    transaction: &'a Transaction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locktime_date: Option<String>,
+    // one entry per input, in input order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    witnesses: Option<Vec<VerboseWitness<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trailing_bytes: Option<TrailingBytes>,
+}
+
+#[derive(Serialize)]
+struct TrailingBytes {
+    count: usize,
+    hex: String,
+}
+
+#[derive(Serialize)]
+struct ScriptOutput {
+    hex: String,
+    asm: String,
+    push_count: usize,
+}
+
+impl ScriptOutput {
+    fn new(script_hex: &str) -> Result<ScriptOutput, hex::FromHexError> {
+        let script = Script::from_bytes(hex::decode(script_hex)?);
+        Ok(ScriptOutput {
+            hex: script_hex.to_string(),
+            asm: script.asm(),
+            push_count: script.push_count(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct InputScripts {
+    #[serde(rename = "scriptSig")]
+    script_sig: ScriptOutput,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    witness: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct OutputScripts {
+    script_pubkey: ScriptOutput,
+}
+
+// Just the scripts and witnesses, for focusing on script analysis
+#[derive(Serialize)]
+struct ScriptsOutput {
+    inputs: Vec<InputScripts>,
+    outputs: Vec<OutputScripts>,
+}
+
+fn scripts(transaction: &Transaction) -> Result<ScriptsOutput, hex::FromHexError> {
+    Ok(ScriptsOutput {
+        inputs: transaction.inputs.iter()
+            .map(|input| Ok(InputScripts {
+                script_sig: ScriptOutput::new(&input.script_sig)?,
+                witness: input.witness.items().iter().map(hex::encode).collect(),
+            }))
+            .collect::<Result<_, hex::FromHexError>>()?,
+        outputs: transaction.outputs.iter()
+            .map(|output| Ok(OutputScripts { script_pubkey: ScriptOutput::new(&output.script_pubkey)? }))
+            .collect::<Result<_, hex::FromHexError>>()?,
+    })
+}
+
 pub fn run(raw_transaction_hex: String) -> Result<String, Box<dyn Error>> {
-    let transaction = decode(raw_transaction_hex)?;
-    Ok(serde_json::to_string_pretty(&transaction)?)
+    run_with_options(raw_transaction_hex, &Options::default())
+}
+
+pub fn run_with_options(raw_transaction_hex: String, options: &Options) -> Result<String, Box<dyn Error>> {
+    if let Some(address) = &options.address_to_script {
+        return Ok(hex::encode(address::script_pubkey(address)?));
+    }
+    if options.explain {
+        let transaction_bytes = hex::decode(raw_transaction_hex).map_err(|e| format!("Hex decoding error: {}", e))?;
+        let (_, trailing) = decode_prefix(&transaction_bytes, false)?;
+        if !trailing.is_empty() {
+            return Err(trailing_bytes_error(&transaction_bytes, trailing));
+        }
+        return Ok(explain::explain(&transaction_bytes)?);
+    }
+    let transaction_bytes = hex::decode(raw_transaction_hex).map_err(|e| format!("Hex decoding error: {}", e))?;
+    let (transaction, trailing) = decode_prefix(&transaction_bytes, options.assume_legacy)?;
+    let mut trailing_bytes = None;
+    if !trailing.is_empty() {
+        if !options.tolerate_trailing {
+            return Err(trailing_bytes_error(&transaction_bytes, trailing));
+        }
+        trailing_bytes = Some(TrailingBytes { count: trailing.len(), hex: hex::encode(trailing) });
+    }
+    if options.outpoints {
+        let lines: Vec<String> = transaction.inputs.iter()
+            .map(|input| {
+                if input.is_coinbase() {
+                    "coinbase".to_string()
+                } else {
+                    input.previous_output.to_string()
+                }
+            })
+            .collect();
+        return Ok(lines.join("\n"));
+    }
+    let pretty_options = pretty::PrettyOptions {
+        trim_amounts: options.trim_amounts,
+        key_style: options.key_style,
+        amount_unit: options.amount_unit,
+    };
+    if options.scripts {
+        return Ok(pretty::to_string_pretty(&scripts(&transaction)?, pretty_options)?);
+    }
+    let mut json = TransactionOutput {
+        transaction: &transaction,
+        hex: None,
+        locktime_date: None,
+        witnesses: None,
+        trailing_bytes,
+    };
+    if options.include_hex {
+        let mut writer = io::HexWriter::new();
+        transaction.consensus_encode(&mut writer)?;
+        json.hex = Some(writer.into_string());
+    }
+    if options.resolve_time {
+        json.locktime_date = locktime_date(&transaction)?;
+    }
+    // a legacy transaction has no witness bytes at all
+    if options.verbose_witness && transaction.is_segwit() {
+        json.witnesses = Some(transaction.inputs.iter().map(|input| input.witness.verbose()).collect());
+    }
+    Ok(pretty::to_string_pretty(&json, pretty_options)?)
+}
+
+#[cfg(feature = "resolve-time")]
+fn locktime_date(transaction: &Transaction) -> Result<Option<String>, Box<dyn Error>> {
+    Ok(transaction.locktime_date())
+}
+
+#[cfg(not(feature = "resolve-time"))]
+fn locktime_date(_transaction: &Transaction) -> Result<Option<String>, Box<dyn Error>> {
+    Err("--resolve-time needs the resolve-time feature enabled".into())
 }
@@ -0,0 +1,174 @@
+use std::io::{self, Write};
+use serde::Serialize;
+use serde_json::ser::{Formatter, PrettyFormatter};
+use crate::transaction::Amount;
+
+// Which names to use for the keys whose spelling differs between Bitcoin Core's
+// JSON and snake_case. The default leaves the keys as the types serialize them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyStyle {
+    #[default]
+    Mixed,
+    Core,
+    Snake,
+}
+
+// (Bitcoin Core, snake_case) spellings of the same key
+const KEY_NAMES: [(&str, &str); 5] = [
+    ("scriptSig", "script_sig"),
+    ("scriptPubKey", "script_pubkey"),
+    ("txinwitness", "witness"),
+    ("locktime", "lock_time"),
+    ("vout", "output_index"),
+];
+
+impl KeyStyle {
+    fn rename(self, key: &str) -> Option<&'static str> {
+        match self {
+            KeyStyle::Mixed => None,
+            KeyStyle::Core => KEY_NAMES.iter().find(|(_, snake)| *snake == key).map(|(core, _)| *core),
+            KeyStyle::Snake => KEY_NAMES.iter().find(|(core, _)| *core == key).map(|(_, snake)| *snake),
+        }
+    }
+}
+
+// The unit amounts are written in. Both writes each amount as an object holding
+// it in satoshis and as a fixed decimal BTC string.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmountUnit {
+    #[default]
+    Btc,
+    Sat,
+    Both,
+}
+
+// Pretty prints like serde_json::to_string_pretty, except for the changes below
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrettyOptions {
+    // floats (which in our JSON are only ever BTC amounts) are written as exact
+    // decimals without trailing zeros, so 1 satoshi is 0.00000001 rather than 1e-8
+    pub trim_amounts: bool,
+    pub key_style: KeyStyle,
+    pub amount_unit: AmountUnit,
+}
+
+pub fn to_string_pretty<T: Serialize>(value: &T, options: PrettyOptions) -> Result<String, serde_json::Error> {
+    let mut buffer = vec![];
+    let formatter = OptionsFormatter { pretty: PrettyFormatter::new(), options, key: None };
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+    value.serialize(&mut serializer)?;
+    Ok(String::from_utf8(buffer).expect("serde_json only writes valid UTF-8"))
+}
+
+impl OptionsFormatter<'_> {
+    // Goes through the pretty formatter so the object is indented like the rest
+    fn write_amount_object<W: ?Sized + Write>(&mut self, writer: &mut W, amount: Amount) -> io::Result<()> {
+        let btc = if self.options.trim_amounts { amount.to_btc_string_trimmed() } else { amount.to_btc_string() };
+        self.pretty.begin_object(writer)?;
+        self.write_key(writer, "sat", true)?;
+        self.pretty.write_u64(writer, amount.to_sat())?;
+        self.pretty.end_object_value(writer)?;
+        self.write_key(writer, "btc", false)?;
+        self.pretty.begin_string(writer)?;
+        self.pretty.write_string_fragment(writer, &btc)?;
+        self.pretty.end_string(writer)?;
+        self.pretty.end_object_value(writer)?;
+        self.pretty.end_object(writer)
+    }
+
+    fn write_key<W: ?Sized + Write>(&mut self, writer: &mut W, key: &str, first: bool) -> io::Result<()> {
+        self.pretty.begin_object_key(writer, first)?;
+        self.pretty.begin_string(writer)?;
+        self.pretty.write_string_fragment(writer, key)?;
+        self.pretty.end_string(writer)?;
+        self.pretty.end_object_key(writer)?;
+        self.pretty.begin_object_value(writer)
+    }
+}
+
+// Everything not affected by the options is left to the pretty formatter
+struct OptionsFormatter<'a> {
+    pretty: PrettyFormatter<'a>,
+    options: PrettyOptions,
+    // while writing an object key, its escaped text is collected here so it can be
+    // renamed before the closing quote
+    key: Option<Vec<u8>>,
+}
+
+impl Formatter for OptionsFormatter<'_> {
+    fn write_f64<W: ?Sized + Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        if self.options.amount_unit == AmountUnit::Btc && !self.options.trim_amounts {
+            return self.pretty.write_f64(writer, value);
+        }
+        // amounts were converted from whole satoshis, so rounding gets them back exactly
+        let amount = Amount::from_sat((value * 100_000_000.0).round() as u64);
+        match self.options.amount_unit {
+            AmountUnit::Btc => writer.write_all(amount.to_btc_string_trimmed().as_bytes()),
+            AmountUnit::Sat => self.pretty.write_u64(writer, amount.to_sat()),
+            AmountUnit::Both => self.write_amount_object(writer, amount),
+        }
+    }
+
+    fn end_string<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        let Some(key) = self.key.take() else {
+            return self.pretty.end_string(writer);
+        };
+        let renamed = std::str::from_utf8(&key).ok().and_then(|key| self.options.key_style.rename(key));
+        writer.write_all(renamed.map_or(key.as_slice(), str::as_bytes))?;
+        self.pretty.end_string(writer)
+    }
+
+    fn write_string_fragment<W: ?Sized + Write>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()> {
+        match &mut self.key {
+            Some(key) => self.pretty.write_string_fragment(key, fragment),
+            None => self.pretty.write_string_fragment(writer, fragment),
+        }
+    }
+
+    fn write_char_escape<W: ?Sized + Write>(&mut self, writer: &mut W, char_escape: serde_json::ser::CharEscape) -> io::Result<()> {
+        match &mut self.key {
+            Some(key) => self.pretty.write_char_escape(key, char_escape),
+            None => self.pretty.write_char_escape(writer, char_escape),
+        }
+    }
+
+    fn begin_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        self.pretty.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(&mut self, writer: &mut W, first: bool) -> io::Result<()> {
+        self.pretty.begin_object_key(writer, first)?;
+        if self.options.key_style != KeyStyle::Mixed {
+            self.key = Some(vec![]);
+        }
+        Ok(())
+    }
+
+    fn begin_object_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.pretty.end_object_value(writer)
+    }
+}
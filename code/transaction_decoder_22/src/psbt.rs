@@ -0,0 +1,156 @@
+use std::io::Read;
+use crate::transaction::{CompactSize, Decodable, Error, Transaction, TxOut};
+
+// BIP174: "psbt" followed by 0xff
+const PSBT_MAGIC: [u8; 5] = [0x70, 0x73, 0x62, 0x74, 0xff];
+
+const PSBT_GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u8 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u8 = 0x02;
+const PSBT_IN_REDEEM_SCRIPT: u8 = 0x04;
+const PSBT_IN_WITNESS_SCRIPT: u8 = 0x05;
+const PSBT_OUT_REDEEM_SCRIPT: u8 = 0x00;
+const PSBT_OUT_WITNESS_SCRIPT: u8 = 0x01;
+
+// A partially signed transaction: the transaction being signed plus what's known
+// so far about each of its inputs and outputs
+#[derive(Debug, Clone)]
+pub struct Psbt {
+    pub unsigned_tx: Transaction,
+    pub inputs: Vec<PsbtInput>,
+    pub outputs: Vec<PsbtOutput>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PsbtInput {
+    // the output being spent, for segwit inputs
+    pub witness_utxo: Option<TxOut>,
+    // (public key, signature) pairs, in the order they appear
+    pub partial_sigs: Vec<(Vec<u8>, Vec<u8>)>,
+    pub redeem_script: Option<Vec<u8>>,
+    pub witness_script: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PsbtOutput {
+    pub redeem_script: Option<Vec<u8>>,
+    pub witness_script: Option<Vec<u8>>,
+}
+
+impl Psbt {
+    // Parses the binary form of a PSBT (version 0). Key types we don't use are skipped.
+    pub fn decode(bytes: &[u8]) -> Result<Psbt, Error> {
+        let r = &mut &bytes[..];
+        let mut magic = [0; 5];
+        r.read_exact(&mut magic).map_err(|_| Error::ParseFailed("invalid psbt magic"))?;
+        if magic != PSBT_MAGIC {
+            return Err(Error::ParseFailed("invalid psbt magic"));
+        }
+
+        let mut unsigned_tx = None;
+        for (key, value) in read_map(r)? {
+            if key == [PSBT_GLOBAL_UNSIGNED_TX] {
+                // always serialized without witnesses, even if it has no inputs
+                unsigned_tx = Some(Transaction::consensus_decode_legacy(&mut value.as_slice())?);
+            }
+        }
+        let unsigned_tx = unsigned_tx.ok_or(Error::ParseFailed("psbt is missing the unsigned transaction"))?;
+
+        let mut inputs = vec![];
+        for _ in 0..unsigned_tx.inputs.len() {
+            let mut input = PsbtInput::default();
+            for (key, value) in read_map(r)? {
+                match key[0] {
+                    PSBT_IN_WITNESS_UTXO => input.witness_utxo = Some(TxOut::consensus_decode(&mut value.as_slice())?),
+                    PSBT_IN_PARTIAL_SIG => input.partial_sigs.push((key[1..].to_vec(), value)),
+                    PSBT_IN_REDEEM_SCRIPT => input.redeem_script = Some(value),
+                    PSBT_IN_WITNESS_SCRIPT => input.witness_script = Some(value),
+                    _ => {}
+                }
+            }
+            inputs.push(input);
+        }
+
+        let mut outputs = vec![];
+        for _ in 0..unsigned_tx.outputs.len() {
+            let mut output = PsbtOutput::default();
+            for (key, value) in read_map(r)? {
+                match key[0] {
+                    PSBT_OUT_REDEEM_SCRIPT => output.redeem_script = Some(value),
+                    PSBT_OUT_WITNESS_SCRIPT => output.witness_script = Some(value),
+                    _ => {}
+                }
+            }
+            outputs.push(output);
+        }
+
+        Ok(Psbt { unsigned_tx, inputs, outputs })
+    }
+}
+
+// A key, whose first byte is its type, and its value
+type KeyValue = (Vec<u8>, Vec<u8>);
+
+// Reads key-value pairs up to the zero length key that ends the map
+fn read_map(r: &mut &[u8]) -> Result<Vec<KeyValue>, Error> {
+    let mut pairs = vec![];
+    loop {
+        let key = read_bytes(r)?;
+        if key.is_empty() {
+            return Ok(pairs);
+        }
+        let value = read_bytes(r)?;
+        pairs.push((key, value));
+    }
+}
+
+fn read_bytes(r: &mut &[u8]) -> Result<Vec<u8>, Error> {
+    let len = CompactSize::consensus_decode(r)
+        .map_err(|_| Error::ParseFailed("psbt ended before the end of a map"))?
+        .0;
+    let mut buffer = vec![0; len as usize];
+    r.read_exact(&mut buffer).map_err(|_| Error::ParseFailed("psbt ended before the end of a map"))?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::Psbt;
+    use crate::transaction::Amount;
+
+    // The segwit test transaction before it was signed, with its signature as a
+    // partial signature and the P2WPKH output it spends (with a made up amount)
+    const PSBT_HEX: &str = "70736274ff0100710200000001d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff028f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7fa9ce0f0000000000160014d850c02b89821f0f189ca7e81756c102241f7f40bff624000001011f20de2e00000000001600142c1fadeda021d2fe6eeb46b97b21cd02fbd143f922020355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101000000";
+
+    #[test]
+    fn test_decode_psbt() {
+        let psbt = Psbt::decode(&hex::decode(PSBT_HEX).unwrap()).unwrap();
+        assert_eq!(
+            psbt.unsigned_tx.txid().to_string(),
+            "17e1fcaae34575d0d1566c1ae64bf4c9f7b7b9df0ff505015d3eb72460fe3a61"
+        );
+        assert_eq!(psbt.inputs.len(), 1);
+        assert_eq!(psbt.outputs.len(), 2);
+
+        let input = &psbt.inputs[0];
+        let witness_utxo = input.witness_utxo.as_ref().unwrap();
+        assert_eq!(witness_utxo.amount, Amount::from_sat(3_071_520));
+        assert_eq!(witness_utxo.script_pubkey, "00142c1fadeda021d2fe6eeb46b97b21cd02fbd143f9");
+
+        assert_eq!(input.partial_sigs.len(), 1);
+        let (pubkey, signature) = &input.partial_sigs[0];
+        assert_eq!(hex::encode(pubkey), "0355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928");
+        assert_eq!(
+            hex::encode(signature),
+            "3044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101"
+        );
+        assert_eq!(input.redeem_script, None);
+        assert_eq!(input.witness_script, None);
+    }
+
+    #[test]
+    fn test_decode_psbt_bad_magic() {
+        let err = Psbt::decode(&hex::decode("70736274fe0000").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "parse failed: invalid psbt magic");
+    }
+}
@@ -0,0 +1,509 @@
+use crate::transaction::Error;
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_PUSHDATA4: u8 = 0x4e;
+pub const OP_1NEGATE: u8 = 0x4f;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_RETURN: u8 = 0x6a;
+pub const OP_DROP: u8 = 0x75;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_HASH160: u8 = 0xa9;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+pub const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+
+pub fn is_op_return(script: &[u8]) -> bool {
+    script.first() == Some(&OP_RETURN)
+}
+
+// A script that can never be spent, either because it starts with OP_RETURN
+// or because it's larger than the consensus limit on script size
+pub fn is_unspendable(script: &[u8]) -> bool {
+    is_op_return(script) || script.len() > MAX_SCRIPT_SIZE
+}
+
+// A witness program is a version byte (OP_0 or OP_1 through OP_16)
+// followed by a single push of 2 to 40 bytes (BIP141)
+pub fn is_witness_program(script: &[u8]) -> bool {
+    if script.len() < 4 || script.len() > 42 {
+        return false;
+    }
+    let version = script[0];
+    if version != OP_0 && !(OP_1..=OP_16).contains(&version) {
+        return false;
+    }
+    script[1] as usize + 2 == script.len()
+}
+
+// A P2WPKH (20 byte) or P2WSH (32 byte) witness program
+pub fn is_v0_witness_program(script: &[u8]) -> bool {
+    (script.len() == 22 || script.len() == 34) && script[0] == OP_0 && is_witness_program(script)
+}
+
+// The standard output script templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptType {
+    P2pk,
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    // a witness program with a version above 1, which isn't defined yet
+    WitnessUnknown,
+    OpReturn,
+    NonStandard,
+}
+
+pub fn script_type(script: &[u8]) -> ScriptType {
+    match script {
+        [OP_DUP, OP_HASH160, 0x14, .., OP_EQUALVERIFY, OP_CHECKSIG] if script.len() == 25 => ScriptType::P2pkh,
+        [OP_HASH160, 0x14, .., OP_EQUAL] if script.len() == 23 => ScriptType::P2sh,
+        [OP_0, 0x14, ..] if script.len() == 22 => ScriptType::P2wpkh,
+        [OP_0, 0x20, ..] if script.len() == 34 => ScriptType::P2wsh,
+        [OP_1, 0x20, ..] if script.len() == 34 => ScriptType::P2tr,
+        // BIP141 only allows 20 or 32 byte programs for version 0, and taproot is only
+        // defined for 32 byte version 1 programs
+        [OP_0 | OP_1, ..] if is_witness_program(script) => ScriptType::NonStandard,
+        _ if is_witness_program(script) => ScriptType::WitnessUnknown,
+        _ if is_op_return(script) => ScriptType::OpReturn,
+        [0x21, .., OP_CHECKSIG] if script.len() == 35 => ScriptType::P2pk,
+        [0x41, .., OP_CHECKSIG] if script.len() == 67 => ScriptType::P2pk,
+        _ => ScriptType::NonStandard,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Instruction<'a> {
+    PushBytes(&'a [u8]),
+    Op(u8),
+}
+
+pub struct Instructions<'a> {
+    script: &'a [u8],
+}
+
+pub fn instructions(script: &[u8]) -> Instructions<'_> {
+    Instructions { script }
+}
+
+impl<'a> Instructions<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if len > self.script.len() {
+            // don't yield anything else after a malformed push
+            self.script = &[];
+            return Err(Error::ParseFailed("push past end of script"));
+        }
+        let (bytes, rest) = self.script.split_at(len);
+        self.script = rest;
+        Ok(bytes)
+    }
+
+    fn take_len(&mut self, size: usize) -> Result<usize, Error> {
+        let bytes = self.take(size)?;
+        let mut buffer = [0; 4];
+        buffer[..size].copy_from_slice(bytes);
+        Ok(u32::from_le_bytes(buffer) as usize)
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&opcode, rest) = self.script.split_first()?;
+        self.script = rest;
+        let len = match opcode {
+            OP_0 => 0,
+            0x01..=0x4b => opcode as usize,
+            OP_PUSHDATA1 => match self.take_len(1) {
+                Ok(len) => len,
+                Err(e) => return Some(Err(e)),
+            },
+            OP_PUSHDATA2 => match self.take_len(2) {
+                Ok(len) => len,
+                Err(e) => return Some(Err(e)),
+            },
+            OP_PUSHDATA4 => match self.take_len(4) {
+                Ok(len) => len,
+                Err(e) => return Some(Err(e)),
+            },
+            _ => return Some(Ok(Instruction::Op(opcode))),
+        };
+        Some(self.take(len).map(Instruction::PushBytes))
+    }
+}
+
+// Returns the data of every push in a push-only script such as a typical scriptSig.
+// Returns None if the script contains any other opcode or a push runs past the end.
+pub fn push_data(script: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut pushes = vec![];
+    for instruction in instructions(script) {
+        match instruction.ok()? {
+            Instruction::PushBytes(bytes) => pushes.push(bytes),
+            Instruction::Op(_) => return None,
+        }
+    }
+    Some(pushes)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TimelockCondition {
+    // the spending transaction's locktime must be at least this block height or timestamp
+    CheckLockTimeVerify(i64),
+    // the spending input's sequence must encode at least this relative locktime
+    CheckSequenceVerify(i64),
+}
+
+#[derive(Debug)]
+pub struct Script(Vec<u8>);
+
+impl Script {
+    pub fn from_bytes(bytes: Vec<u8>) -> Script {
+        Script(bytes)
+    }
+
+    // Renders the script the same way as Bitcoin Core's `asm` fields:
+    // pushes of up to 4 bytes as numbers, longer pushes as hex and opcodes by name
+    pub fn asm(&self) -> String {
+        let mut asm = vec![];
+        for instruction in instructions(&self.0) {
+            match instruction {
+                Ok(Instruction::PushBytes(bytes)) if bytes.len() <= 4 => {
+                    asm.push(script_num(bytes).to_string())
+                }
+                Ok(Instruction::PushBytes(bytes)) => asm.push(hex::encode(bytes)),
+                Ok(Instruction::Op(opcode)) => asm.push(opcode_name(opcode).to_string()),
+                Err(_) => asm.push("[error]".to_string()),
+            }
+        }
+        asm.join(" ")
+    }
+
+    // The number of data pushes, including OP_0's empty push but not the OP_1 to
+    // OP_16 number opcodes. Stops counting at a malformed push.
+    pub fn push_count(&self) -> usize {
+        instructions(&self.0)
+            .map_while(Result::ok)
+            .filter(|instruction| matches!(instruction, Instruction::PushBytes(_)))
+            .count()
+    }
+
+    // The version and program of a witness program, whatever its version and length
+    pub fn witness_program(&self) -> Option<(u8, Vec<u8>)> {
+        if !is_witness_program(&self.0) {
+            return None;
+        }
+        let version = match self.0[0] {
+            OP_0 => 0,
+            opcode => opcode - OP_1 + 1,
+        };
+        Some((version, self.0[2..].to_vec()))
+    }
+
+    // Standardness requires every push in a scriptSig to use the shortest encoding,
+    // so that a relayed transaction can't be malleated by re-encoding its pushes.
+    // A malformed push counts as not minimal.
+    pub fn uses_minimal_pushes(&self) -> bool {
+        let mut instructions = instructions(&self.0);
+        loop {
+            let Some(&opcode) = instructions.script.first() else {
+                return true;
+            };
+            match instructions.next() {
+                Some(Ok(Instruction::PushBytes(data))) if !is_minimal_push(opcode, data) => return false,
+                Some(Err(_)) => return false,
+                _ => {}
+            }
+        }
+    }
+
+    // Recognizes scripts that start with `<n> OP_CHECKLOCKTIMEVERIFY OP_DROP` or
+    // `<n> OP_CHECKSEQUENCEVERIFY OP_DROP`, as used to add a timelock to another script
+    pub fn timelock_condition(&self) -> Option<TimelockCondition> {
+        let mut instructions = instructions(&self.0);
+        let value = match instructions.next()?.ok()? {
+            // the opcode's numbers are limited to 5 bytes
+            Instruction::PushBytes(bytes) if bytes.len() <= 5 => script_num(bytes),
+            Instruction::Op(opcode) if (OP_1..=OP_16).contains(&opcode) => (opcode - OP_1 + 1) as i64,
+            _ => return None,
+        };
+        let condition = match instructions.next()?.ok()? {
+            Instruction::Op(OP_CHECKLOCKTIMEVERIFY) => TimelockCondition::CheckLockTimeVerify(value),
+            Instruction::Op(OP_CHECKSEQUENCEVERIFY) => TimelockCondition::CheckSequenceVerify(value),
+            _ => return None,
+        };
+        match instructions.next()?.ok()? {
+            Instruction::Op(OP_DROP) => Some(condition),
+            _ => None,
+        }
+    }
+}
+
+// Mirrors Bitcoin Core's CheckMinimalPush
+fn is_minimal_push(opcode: u8, data: &[u8]) -> bool {
+    match data {
+        [] => opcode == OP_0,
+        // these have their own opcodes
+        [n @ 1..=16] => opcode == OP_1 + n - 1,
+        [0x81] => opcode == OP_1NEGATE,
+        _ if data.len() <= 75 => opcode as usize == data.len(),
+        _ if data.len() <= 255 => opcode == OP_PUSHDATA1,
+        _ if data.len() <= 65535 => opcode == OP_PUSHDATA2,
+        _ => true,
+    }
+}
+
+// Interprets a minimally encoded little-endian number with a sign bit
+pub fn script_num(bytes: &[u8]) -> i64 {
+    let Some(&last) = bytes.last() else {
+        return 0;
+    };
+    let mut result = 0_i64;
+    for (i, byte) in bytes.iter().enumerate() {
+        result |= (*byte as i64) << (8 * i);
+    }
+    if last & 0x80 != 0 {
+        -(result & !(0x80_i64 << (8 * (bytes.len() - 1))))
+    } else {
+        result
+    }
+}
+
+pub fn opcode_name(opcode: u8) -> &'static str {
+    match opcode {
+        0x00 => "0",
+        0x4c => "OP_PUSHDATA1",
+        0x4d => "OP_PUSHDATA2",
+        0x4e => "OP_PUSHDATA4",
+        0x4f => "-1",
+        0x50 => "OP_RESERVED",
+        0x51 => "1",
+        0x52 => "2",
+        0x53 => "3",
+        0x54 => "4",
+        0x55 => "5",
+        0x56 => "6",
+        0x57 => "7",
+        0x58 => "8",
+        0x59 => "9",
+        0x5a => "10",
+        0x5b => "11",
+        0x5c => "12",
+        0x5d => "13",
+        0x5e => "14",
+        0x5f => "15",
+        0x60 => "16",
+        0x61 => "OP_NOP",
+        0x62 => "OP_VER",
+        0x63 => "OP_IF",
+        0x64 => "OP_NOTIF",
+        0x65 => "OP_VERIF",
+        0x66 => "OP_VERNOTIF",
+        0x67 => "OP_ELSE",
+        0x68 => "OP_ENDIF",
+        0x69 => "OP_VERIFY",
+        0x6a => "OP_RETURN",
+        0x6b => "OP_TOALTSTACK",
+        0x6c => "OP_FROMALTSTACK",
+        0x6d => "OP_2DROP",
+        0x6e => "OP_2DUP",
+        0x6f => "OP_3DUP",
+        0x70 => "OP_2OVER",
+        0x71 => "OP_2ROT",
+        0x72 => "OP_2SWAP",
+        0x73 => "OP_IFDUP",
+        0x74 => "OP_DEPTH",
+        0x75 => "OP_DROP",
+        0x76 => "OP_DUP",
+        0x77 => "OP_NIP",
+        0x78 => "OP_OVER",
+        0x79 => "OP_PICK",
+        0x7a => "OP_ROLL",
+        0x7b => "OP_ROT",
+        0x7c => "OP_SWAP",
+        0x7d => "OP_TUCK",
+        0x7e => "OP_CAT",
+        0x7f => "OP_SUBSTR",
+        0x80 => "OP_LEFT",
+        0x81 => "OP_RIGHT",
+        0x82 => "OP_SIZE",
+        0x83 => "OP_INVERT",
+        0x84 => "OP_AND",
+        0x85 => "OP_OR",
+        0x86 => "OP_XOR",
+        0x87 => "OP_EQUAL",
+        0x88 => "OP_EQUALVERIFY",
+        0x89 => "OP_RESERVED1",
+        0x8a => "OP_RESERVED2",
+        0x8b => "OP_1ADD",
+        0x8c => "OP_1SUB",
+        0x8d => "OP_2MUL",
+        0x8e => "OP_2DIV",
+        0x8f => "OP_NEGATE",
+        0x90 => "OP_ABS",
+        0x91 => "OP_NOT",
+        0x92 => "OP_0NOTEQUAL",
+        0x93 => "OP_ADD",
+        0x94 => "OP_SUB",
+        0x95 => "OP_MUL",
+        0x96 => "OP_DIV",
+        0x97 => "OP_MOD",
+        0x98 => "OP_LSHIFT",
+        0x99 => "OP_RSHIFT",
+        0x9a => "OP_BOOLAND",
+        0x9b => "OP_BOOLOR",
+        0x9c => "OP_NUMEQUAL",
+        0x9d => "OP_NUMEQUALVERIFY",
+        0x9e => "OP_NUMNOTEQUAL",
+        0x9f => "OP_LESSTHAN",
+        0xa0 => "OP_GREATERTHAN",
+        0xa1 => "OP_LESSTHANOREQUAL",
+        0xa2 => "OP_GREATERTHANOREQUAL",
+        0xa3 => "OP_MIN",
+        0xa4 => "OP_MAX",
+        0xa5 => "OP_WITHIN",
+        0xa6 => "OP_RIPEMD160",
+        0xa7 => "OP_SHA1",
+        0xa8 => "OP_SHA256",
+        0xa9 => "OP_HASH160",
+        0xaa => "OP_HASH256",
+        0xab => "OP_CODESEPARATOR",
+        0xac => "OP_CHECKSIG",
+        0xad => "OP_CHECKSIGVERIFY",
+        0xae => "OP_CHECKMULTISIG",
+        0xaf => "OP_CHECKMULTISIGVERIFY",
+        0xb0 => "OP_NOP1",
+        0xb1 => "OP_CHECKLOCKTIMEVERIFY",
+        0xb2 => "OP_CHECKSEQUENCEVERIFY",
+        0xb3 => "OP_NOP4",
+        0xb4 => "OP_NOP5",
+        0xb5 => "OP_NOP6",
+        0xb6 => "OP_NOP7",
+        0xb7 => "OP_NOP8",
+        0xb8 => "OP_NOP9",
+        0xb9 => "OP_NOP10",
+        0xba => "OP_CHECKSIGADD",
+        _ => "OP_UNKNOWN",
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{script_type, Script, ScriptType, TimelockCondition};
+
+    #[test]
+    fn test_witness_program() {
+        let p2wpkh = hex::decode("00146f048d1381aa546a3e89e87f7549efc45f150b7f").unwrap();
+        assert_eq!(
+            Script::from_bytes(p2wpkh.clone()).witness_program(),
+            Some((0, p2wpkh[2..].to_vec()))
+        );
+        assert_eq!(script_type(&p2wpkh), ScriptType::P2wpkh);
+
+        let p2tr = hex::decode("5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c").unwrap();
+        assert_eq!(
+            Script::from_bytes(p2tr.clone()).witness_program(),
+            Some((1, p2tr[2..].to_vec()))
+        );
+        assert_eq!(script_type(&p2tr), ScriptType::P2tr);
+
+        // a 21 byte version 0 program has the shape of a witness program but isn't valid
+        let mut invalid = vec![0x00, 0x15];
+        invalid.extend([0xab; 21]);
+        assert_eq!(Script::from_bytes(invalid.clone()).witness_program(), Some((0, vec![0xab; 21])));
+        assert_eq!(script_type(&invalid), ScriptType::NonStandard);
+
+        // neither is a 20 byte version 1 program
+        let mut invalid = vec![0x51, 0x14];
+        invalid.extend([0xab; 20]);
+        assert_eq!(script_type(&invalid), ScriptType::NonStandard);
+
+        // later versions are left for future soft forks
+        let mut future = vec![0x52, 0x02];
+        future.extend([0xab; 2]);
+        assert_eq!(Script::from_bytes(future.clone()).witness_program(), Some((2, vec![0xab; 2])));
+        assert_eq!(script_type(&future), ScriptType::WitnessUnknown);
+
+        assert_eq!(Script::from_bytes(vec![0x6a, 0x02, 0xab, 0xcd]).witness_program(), None);
+    }
+
+    #[test]
+    fn test_uses_minimal_pushes() {
+        // <signature> <pubkey> from the legacy test transaction's first input
+        let script_sig = hex::decode("4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5").unwrap();
+        assert!(Script::from_bytes(script_sig).uses_minimal_pushes());
+        // OP_0 OP_2 OP_1NEGATE
+        assert!(Script::from_bytes(vec![0x00, 0x52, 0x4f]).uses_minimal_pushes());
+
+        // a direct push of the single byte 2, which should be OP_2
+        assert!(!Script::from_bytes(vec![0x01, 0x02]).uses_minimal_pushes());
+        // an empty push with OP_PUSHDATA1, which should be OP_0
+        assert!(!Script::from_bytes(vec![0x4c, 0x00]).uses_minimal_pushes());
+        // 3 bytes with OP_PUSHDATA1, which fit in a direct push
+        assert!(!Script::from_bytes(vec![0x4c, 0x03, 0xaa, 0xbb, 0xcc]).uses_minimal_pushes());
+        // a push running past the end of the script
+        assert!(!Script::from_bytes(vec![0x05, 0xaa]).uses_minimal_pushes());
+    }
+
+    #[test]
+    fn test_push_count() {
+        // <signature> <pubkey> from the legacy test transaction's first input
+        let p2pkh = hex::decode("4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5").unwrap();
+        assert_eq!(Script::from_bytes(p2pkh).push_count(), 2);
+
+        // OP_0 <signature> <signature> <2 of 3 multisig redeem script>, with made up signatures and keys
+        let mut p2sh_multisig = vec![0x00];
+        for _ in 0..2 {
+            p2sh_multisig.push(0x47);
+            p2sh_multisig.extend([0x30; 71]);
+        }
+        p2sh_multisig.extend([0x4c, 0x69, 0x52]);
+        for _ in 0..3 {
+            p2sh_multisig.push(0x21);
+            p2sh_multisig.extend([0x02; 33]);
+        }
+        p2sh_multisig.extend([0x53, 0xae]);
+        assert_eq!(Script::from_bytes(p2sh_multisig).push_count(), 4);
+
+        // OP_RETURN "hello"
+        assert_eq!(Script::from_bytes(hex::decode("6a0568656c6c6f").unwrap()).push_count(), 1);
+        // OP_1 isn't a data push, and nothing is counted from a push running past the end
+        assert_eq!(Script::from_bytes(vec![0x51, 0x01, 0xaa, 0x05, 0xaa]).push_count(), 1);
+    }
+
+    #[test]
+    fn test_cltv_p2pkh() {
+        // 500000 OP_CHECKLOCKTIMEVERIFY OP_DROP OP_DUP OP_HASH160 <pubkey hash> OP_EQUALVERIFY OP_CHECKSIG
+        let script = Script::from_bytes(hex::decode("0320a107b17576a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac").unwrap());
+        assert_eq!(script.timelock_condition(), Some(TimelockCondition::CheckLockTimeVerify(500_000)));
+        assert_eq!(
+            script.asm(),
+            "500000 OP_CHECKLOCKTIMEVERIFY OP_DROP OP_DUP OP_HASH160 4ef88a0b04e3ad6d1888da4be260d6735e0d3084 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        // 144 OP_CHECKSEQUENCEVERIFY OP_DROP <pubkey> OP_CHECKSIG
+        let script = Script::from_bytes(hex::decode("029000b275210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928ac").unwrap());
+        assert_eq!(script.timelock_condition(), Some(TimelockCondition::CheckSequenceVerify(144)));
+
+        // 16 OP_CHECKSEQUENCEVERIFY OP_DROP
+        let script = Script::from_bytes(hex::decode("60b275").unwrap());
+        assert_eq!(script.timelock_condition(), Some(TimelockCondition::CheckSequenceVerify(16)));
+    }
+
+    #[test]
+    fn test_no_timelock() {
+        let script = Script::from_bytes(hex::decode("76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac").unwrap());
+        assert_eq!(script.timelock_condition(), None);
+    }
+}
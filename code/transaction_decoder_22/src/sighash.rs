@@ -0,0 +1,135 @@
+use crate::hashes;
+use crate::signature::{SIGHASH_ANYONECANPAY, SIGHASH_NONE, SIGHASH_SINGLE};
+use crate::transaction::{Amount, Transaction, TxOut};
+
+// Bitcoin Core returns the number 1 as the hash instead of failing when there's
+// nothing to sign, and since that's part of consensus it has to be reproduced.
+// It's little-endian like the other hashes, so it displays as 00..01.
+pub const SIGHASH_ONE: [u8; 32] = {
+    let mut one = [0; 32];
+    one[0] = 1;
+    one
+};
+
+impl Transaction {
+    // The hash a pre-segwit signature for the input commits to. script_code is the
+    // script being satisfied (the scriptPubKey, or the redeem script for P2SH) with
+    // any OP_CODESEPARATORs already removed.
+    pub fn legacy_sighash(&self, input_index: usize, script_code: &[u8], sighash_type: u32) -> [u8; 32] {
+        if input_index >= self.inputs.len() {
+            return SIGHASH_ONE;
+        }
+        let base_type = sighash_type & 0x1f;
+        // the SIGHASH_SINGLE bug: there's no output paired with the input, so
+        // the signature commits to nothing and can be reused on anything
+        if base_type == SIGHASH_SINGLE && input_index >= self.outputs.len() {
+            return SIGHASH_ONE;
+        }
+
+        let mut copy = self.clone_stripped();
+        for (i, input) in copy.inputs.iter_mut().enumerate() {
+            if i == input_index {
+                input.script_sig = hex::encode(script_code);
+            } else {
+                input.script_sig = String::new();
+                // let the other inputs be replaced without invalidating this signature
+                if base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE {
+                    input.sequence = 0;
+                }
+            }
+        }
+        if sighash_type & SIGHASH_ANYONECANPAY != 0 {
+            copy.inputs = vec![copy.inputs.swap_remove(input_index)];
+        }
+        match base_type {
+            SIGHASH_NONE => copy.outputs.clear(),
+            SIGHASH_SINGLE => {
+                // only the output at the same index is signed, the earlier ones are
+                // blanked out with an amount of -1 and an empty script
+                copy.outputs.truncate(input_index + 1);
+                for output in copy.outputs[..input_index].iter_mut() {
+                    *output = TxOut::new(Amount::from_sat(u64::MAX), String::new());
+                }
+            }
+            _ => {}
+        }
+
+        let mut bytes = copy.to_bytes();
+        bytes.extend(sighash_type.to_le_bytes());
+        hashes::sha256d(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::SIGHASH_ONE;
+    use crate::signature::{SIGHASH_ALL, SIGHASH_ANYONECANPAY, SIGHASH_SINGLE};
+    use crate::test_vectors::LEGACY_TRANSACTION_HEX;
+    use crate::transaction::{Amount, Decodable, OutPoint, Transaction, TxIn, TxOut, Txid, Version};
+
+    // two inputs but only one output
+    fn transaction() -> Transaction {
+        Transaction {
+            version: Version(1),
+            inputs: vec![
+                TxIn::new(OutPoint { txid: Txid([1; 32]), vout: 0 }, String::new()),
+                TxIn::new(OutPoint { txid: Txid([2; 32]), vout: 1 }, String::new()),
+            ],
+            outputs: vec![TxOut::new(
+                Amount::from_sat(50_000),
+                "76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac".to_string(),
+            )],
+            lock_time: 0,
+        }
+    }
+
+    #[test]
+    fn test_sighash_single_bug() {
+        let transaction = transaction();
+        let script_code = hex::decode("76a91476c0c8f2fc403c5edaea365f6a284317b9cdf72588ac").unwrap();
+
+        let sighash = transaction.legacy_sighash(1, &script_code, SIGHASH_SINGLE);
+        assert_eq!(sighash, SIGHASH_ONE);
+        let mut displayed = sighash;
+        displayed.reverse();
+        assert_eq!(hex::encode(displayed), "0000000000000000000000000000000000000000000000000000000000000001");
+        assert_eq!(transaction.legacy_sighash(1, &script_code, SIGHASH_SINGLE | SIGHASH_ANYONECANPAY), SIGHASH_ONE);
+
+        // input 0 has a matching output, and with SIGHASH_ALL input 1 is fine too
+        assert_ne!(transaction.legacy_sighash(0, &script_code, SIGHASH_SINGLE), SIGHASH_ONE);
+        assert_ne!(transaction.legacy_sighash(1, &script_code, SIGHASH_ALL), SIGHASH_ONE);
+    }
+
+    #[test]
+    fn test_sighash_commits_to_sighash_type() {
+        let transaction = transaction();
+        let script_code = hex::decode("76a91476c0c8f2fc403c5edaea365f6a284317b9cdf72588ac").unwrap();
+        let all = transaction.legacy_sighash(0, &script_code, SIGHASH_ALL);
+        let single = transaction.legacy_sighash(0, &script_code, SIGHASH_SINGLE);
+        assert_ne!(all, single);
+
+        // SIGHASH_ALL covers the other input's sequence, SIGHASH_SINGLE doesn't
+        let mut replaced = transaction.clone();
+        replaced.inputs[1].sequence = 0xfffffffd;
+        assert_ne!(replaced.legacy_sighash(0, &script_code, SIGHASH_ALL), all);
+        assert_eq!(replaced.legacy_sighash(0, &script_code, SIGHASH_SINGLE), single);
+    }
+
+    #[test]
+    fn test_sighash_all() {
+        // both inputs spend P2PKH outputs, and their SIGHASH_ALL signatures verify
+        // against these hashes with the public keys in their scriptSigs
+        let bytes = hex::decode(LEGACY_TRANSACTION_HEX).unwrap();
+        let transaction = Transaction::consensus_decode(&mut bytes.as_slice()).unwrap();
+        let script_code = hex::decode("76a91499ec9e3a0799e5809736c2eb801c09c9a7c968e488ac").unwrap();
+        assert_eq!(
+            hex::encode(transaction.legacy_sighash(0, &script_code, SIGHASH_ALL)),
+            "6cb4eec9fe59f766d45e4d34f600d8781040839ecdc8e6f27558137c8e9216ed"
+        );
+        let script_code = hex::decode("76a914502e0760781f06235129f12a8d35b664893e8a3788ac").unwrap();
+        assert_eq!(
+            hex::encode(transaction.legacy_sighash(1, &script_code, SIGHASH_ALL)),
+            "55930d1da9a768e77da07d014bc4cba14161ddd08250b237617b9c855989afb5"
+        );
+    }
+}
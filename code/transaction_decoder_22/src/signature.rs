@@ -0,0 +1,146 @@
+pub const SIGHASH_ALL: u32 = 0x01;
+pub const SIGHASH_NONE: u32 = 0x02;
+pub const SIGHASH_SINGLE: u32 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+// A signature in a scriptSig or witness is a DER encoded signature followed by
+// a single byte indicating which parts of the transaction it commits to
+pub fn parse_sighash_type(sig: &[u8]) -> Option<u32> {
+    if sig.len() < 2 || sig[0] != 0x30 {
+        return None;
+    }
+    let sighash_type = *sig.last()? as u32;
+    match sighash_type & !SIGHASH_ANYONECANPAY {
+        SIGHASH_ALL | SIGHASH_NONE | SIGHASH_SINGLE => Some(sighash_type),
+        _ => None,
+    }
+}
+
+pub fn sighash_type_to_string(sighash_type: u32) -> String {
+    let base = match sighash_type & !SIGHASH_ANYONECANPAY {
+        SIGHASH_ALL => "ALL",
+        SIGHASH_NONE => "NONE",
+        SIGHASH_SINGLE => "SINGLE",
+        _ => return format!("{:#04x}", sighash_type),
+    };
+    if sighash_type & SIGHASH_ANYONECANPAY != 0 {
+        format!("{}|ANYONECANPAY", base)
+    } else {
+        base.to_string()
+    }
+}
+
+// Half of the secp256k1 curve order. BIP62 requires the S value of a signature
+// to be at most this, since both S and order - S produce a valid signature.
+const HALF_CURVE_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d,
+    0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+// Checks a signature (including its trailing sighash byte) against the strict DER
+// rules from BIP66 and the low-S rule that Bitcoin Core enforces for standardness.
+// The format is: 0x30 [total-length] 0x02 [R-length] [R] 0x02 [S-length] [S] [sighash]
+pub fn is_valid_der_signature(sig: &[u8]) -> bool {
+    // minimum and maximum size constraints
+    if sig.len() < 9 || sig.len() > 73 {
+        return false;
+    }
+    // a signature is of type compound
+    if sig[0] != 0x30 {
+        return false;
+    }
+    // the length covers the entire signature, minus the sighash byte
+    if sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    // the length of the signature matches the sum of the length of the elements
+    if len_r + len_s + 7 != sig.len() {
+        return false;
+    }
+
+    let r = &sig[4..4 + len_r];
+    let s = &sig[6 + len_r..6 + len_r + len_s];
+    if sig[2] != 0x02 || !is_valid_der_integer(r) {
+        return false;
+    }
+    if sig[4 + len_r] != 0x02 || !is_valid_der_integer(s) {
+        return false;
+    }
+
+    is_low_s(s)
+}
+
+// A DER integer can't be empty or negative and can't have a leading zero
+// unless it's needed to keep the next byte from being read as a sign bit
+fn is_valid_der_integer(int: &[u8]) -> bool {
+    if int.is_empty() || int[0] & 0x80 != 0 {
+        return false;
+    }
+    !(int.len() > 1 && int[0] == 0x00 && int[1] & 0x80 == 0)
+}
+
+fn is_low_s(s: &[u8]) -> bool {
+    let s = match s.iter().position(|&b| b != 0) {
+        Some(start) => &s[start..],
+        None => return true,
+    };
+    if s.len() > 32 {
+        return false;
+    }
+    let mut padded = [0_u8; 32];
+    padded[32 - s.len()..].copy_from_slice(s);
+    padded <= HALF_CURVE_ORDER
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    #[test]
+    fn test_sighash_all() {
+        let sig = hex::decode("3044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101").unwrap();
+        assert_eq!(parse_sighash_type(&sig), Some(SIGHASH_ALL));
+        assert_eq!(sighash_type_to_string(SIGHASH_ALL), "ALL");
+    }
+
+    #[test]
+    fn test_sighash_single_anyonecanpay() {
+        let sig = hex::decode("3044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356183").unwrap();
+        assert_eq!(parse_sighash_type(&sig), Some(SIGHASH_SINGLE | SIGHASH_ANYONECANPAY));
+        assert_eq!(sighash_type_to_string(0x83), "SINGLE|ANYONECANPAY");
+    }
+
+    #[test]
+    fn test_sighash_invalid() {
+        assert_eq!(parse_sighash_type(&[0x30, 0x04]), None);
+        assert_eq!(parse_sighash_type(&[0x02, 0x01]), None);
+        assert_eq!(parse_sighash_type(&[]), None);
+    }
+
+    #[test]
+    fn test_valid_der_signature() {
+        let sig = hex::decode("3045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c4701").unwrap();
+        assert!(is_valid_der_signature(&sig));
+    }
+
+    #[test]
+    fn test_der_signature_extra_byte() {
+        let sig = hex::decode("3045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c470100").unwrap();
+        assert!(!is_valid_der_signature(&sig));
+    }
+
+    #[test]
+    fn test_der_signature_high_s() {
+        // S = order - 1
+        let sig = hex::decode("3046022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e9443022100fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd036414001").unwrap();
+        assert!(!is_valid_der_signature(&sig));
+    }
+}
@@ -1,14 +1,31 @@
 use serde::{Serialize, Serializer};
 use serde::ser::SerializeStruct;
 use std::io::{Read, Write};
+use std::collections::{BTreeSet, HashSet};
 use std::fmt;
-use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::ops::Range;
+use crate::address::{self, Network};
+use crate::hashes;
+use crate::io::CountingReader;
+use crate::script::{self, Instruction, Script, ScriptType, TimelockCondition};
+use crate::signature;
 
 #[derive(Debug)]
 pub enum Error {
     Io(std::io::Error),
     ParseFailed(&'static str),
-    UnsupportedSegwitFlag(u8),
+    // the flag byte following a zero input count, and its position in the transaction.
+    // decode_strict also fills in how many inputs and outputs the bytes after the
+    // flag hold when read as if the flag were 1, where they could be read.
+    UnsupportedSegwitFlag { flag: u8, offset: usize, inputs: Option<u64>, outputs: Option<u64> },
+    InvalidFee(&'static str),
+    // the named amounts add up to more satoshis than a u64 holds
+    AmountOverflow(&'static str),
+    // the input ran out while reading the named field
+    UnexpectedEof(&'static str),
+    // the input ran out after reading fewer inputs than the input count declared
+    InputCountMismatch { declared: u64, read: u64 },
 }
 
 impl fmt::Display for Error {
@@ -16,15 +33,49 @@ impl fmt::Display for Error {
         match *self {
             Error::Io(ref e) => write!(f, "IO error: {}", e),
             Error::ParseFailed(s) => write!(f, "parse failed: {}", s),
-            Error::UnsupportedSegwitFlag(swflag) =>
-                write!(f, "unsupported segwit version: {}", swflag),
+            Error::UnsupportedSegwitFlag { flag, offset, inputs, outputs } => {
+                write!(f, "unsupported segwit flag {:#04x} at offset {}", flag, offset)?;
+                match (inputs, outputs) {
+                    (Some(inputs), Some(outputs)) => write!(f, " (followed by {} inputs and {} outputs)", inputs, outputs),
+                    (Some(inputs), None) => write!(f, " (followed by {} inputs)", inputs),
+                    _ => Ok(()),
+                }
+            }
+            Error::InvalidFee(s) => write!(f, "invalid fee: {}", s),
+            Error::AmountOverflow(s) => write!(f, "{} add up to more than {} satoshis", s, u64::MAX),
+            Error::UnexpectedEof(field) => write!(f, "unexpected end of input while reading {}", field),
+            Error::InputCountMismatch { declared, read } => write!(
+                f,
+                "parse failed: declared input count exceeds available inputs (declared {}, read {})",
+                declared, read,
+            ),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
-#[derive(Debug)]
+// Used with map_err to say which field was being read when the input ran out.
+// Only the innermost field is kept, so an error already given a field passes through.
+fn eof_context(field: &'static str) -> impl Fn(Error) -> Error {
+    move |e| match e {
+        Error::Io(ref io) if io.kind() == std::io::ErrorKind::UnexpectedEof => Error::UnexpectedEof(field),
+        e => e,
+    }
+}
+
+// Used with map_err on each input after eof_context. Running out of bytes exactly
+// where another input should start, after at least one whole input, means the count
+// promised more inputs than there are. Running out partway through an input, or
+// at the start of the first, is left as an ordinary truncation.
+fn input_count_context(declared: u64, read: usize, at_input_start: bool) -> impl Fn(Error) -> Error {
+    move |e| match e {
+        Error::UnexpectedEof(_) if read > 0 && at_input_start => Error::InputCountMismatch { declared, read: read as u64 },
+        e => e,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Transaction {
     pub version: Version,
     pub inputs: Vec<TxIn>,
@@ -34,84 +85,864 @@ pub struct Transaction {
 
 impl Transaction {
     pub fn txid(&self) -> Txid {
-        let mut txid_data = Vec::new();
-        self.version.consensus_encode(&mut txid_data).unwrap();
-        self.inputs.consensus_encode(&mut txid_data).unwrap();
-        self.outputs.consensus_encode(&mut txid_data).unwrap();
-        self.lock_time.consensus_encode(&mut txid_data).unwrap();
-        Txid::new(txid_data)
+        Txid::new(self.legacy_serialization())
+    }
+
+    // The serialization without the segwit marker, flag and witnesses. These are
+    // exactly the bytes that get double hashed into the txid.
+    pub fn legacy_serialization(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.version.consensus_encode(&mut bytes).unwrap();
+        self.inputs.consensus_encode(&mut bytes).unwrap();
+        self.outputs.consensus_encode(&mut bytes).unwrap();
+        self.lock_time.consensus_encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    // The witness txid from BIP141 is the hash of the full serialization, so for
+    // a transaction without witnesses it's the same as the txid
+    pub fn wtxid(&self) -> Txid {
+        Txid::new(self.to_bytes())
+    }
+
+    pub fn is_segwit(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    // A copy of the transaction without any witnesses, which serializes
+    // to the same legacy bytes that the txid is computed from
+    pub fn clone_stripped(&self) -> Transaction {
+        let mut stripped = self.clone();
+        for input in stripped.inputs.iter_mut() {
+            input.witness = Witness::new();
+        }
+        stripped
+    }
+
+    // Keeps only the outputs the predicate accepts, e.g. to drop an output before re-signing
+    pub fn retain_outputs<F: Fn(&TxOut) -> bool>(&mut self, f: F) {
+        self.outputs.retain(f);
+    }
+
+    pub fn map_output_amounts<F: Fn(Amount) -> Amount>(&mut self, f: F) {
+        for output in self.outputs.iter_mut() {
+            output.amount = f(output.amount);
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.serialized_len());
+        self.consensus_encode(&mut bytes).unwrap();
+        bytes
+    }
+
+    // Size in bytes without the segwit marker, flag and witnesses.
+    // Adds up the size of each field instead of encoding the transaction.
+    pub fn base_size(&self) -> usize {
+        let inputs: usize = self.inputs.iter().map(|input| input.serialized_len()).sum();
+        let outputs: usize = self.outputs.iter().map(|output| output.serialized_len()).sum();
+        4 + CompactSize(self.inputs.len() as u64).serialized_len()
+            + inputs
+            + CompactSize(self.outputs.len() as u64).serialized_len()
+            + outputs
+            + 4
+    }
+
+    // Size in bytes of the full serialization, including any witness data
+    pub fn serialized_len(&self) -> usize {
+        if !self.is_segwit() {
+            return self.base_size();
+        }
+        let marker_and_flag = 2;
+        let witnesses: usize = self.inputs.iter().map(|input| input.witness.serialized_len()).sum();
+        self.base_size() + marker_and_flag + witnesses
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.serialized_len()
+    }
+
+    // BIP141 defines weight as base size * 3 + total size, so every non-witness
+    // byte counts for 4 weight units while the marker, flag and witness bytes
+    // only count for 1
+    pub fn weight(&self) -> usize {
+        self.base_size() * 3 + self.total_size()
+    }
+
+    // What each input adds to the weight, in input order. Unlike TxIn::weight this
+    // counts the 00 byte an input without a witness has in a segwit transaction,
+    // so these plus the outputs' weights and the fixed fields add up to weight().
+    pub fn input_weights(&self) -> Vec<usize> {
+        let segwit = self.is_segwit();
+        self.inputs.iter()
+            .map(|input| input.weight() + (segwit && input.witness.is_empty()) as usize)
+            .collect()
+    }
+
+    // Bitcoin Core won't relay a transaction whose size without witnesses is under
+    // 65 bytes, since a 64 byte one could be mistaken for an inner node of a
+    // block's merkle tree (CVE-2017-12842)
+    pub fn is_relayable_size(&self) -> bool {
+        self.base_size() >= MIN_STANDARD_TX_NONWITNESS_SIZE
+    }
+
+    // Virtual size is the weight divided by 4, rounded up
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+
+    // The fee is whatever the inputs are worth beyond the outputs. A transaction
+    // doesn't include the amounts of the outputs it spends, so they have to be
+    // passed in as prevouts, one per input and in the same order.
+    pub fn fee(&self, prevouts: &[Amount]) -> Result<Amount, Error> {
+        self.spent_outputs_total(prevouts)?
+            .checked_sub(self.created_outputs_total()?)
+            .ok_or(Error::InvalidFee("outputs are worth more than the inputs"))
+    }
+
+    // The value of the outputs being spent, given their amounts as prevouts
+    pub fn spent_outputs_total(&self, prevouts: &[Amount]) -> Result<Amount, Error> {
+        if prevouts.len() != self.inputs.len() {
+            return Err(Error::InvalidFee("expected one prevout amount per input"));
+        }
+        prevouts.iter()
+            .try_fold(Amount::from_sat(0), |total, amount| total.checked_add(*amount))
+            .ok_or(Error::AmountOverflow("prevout amounts"))
+    }
+
+    // The value of the outputs this transaction creates
+    pub fn created_outputs_total(&self) -> Result<Amount, Error> {
+        self.outputs.iter()
+            .try_fold(Amount::from_sat(0), |total, output| total.checked_add(output.amount))
+            .ok_or(Error::AmountOverflow("output amounts"))
+    }
+
+    // Fee rate in sat/vB
+    pub fn feerate(&self, prevouts: &[Amount]) -> Result<f64, Error> {
+        let fee = self.fee(prevouts)?;
+        Ok(fee.0 as f64 / self.vsize() as f64)
+    }
+
+    pub fn feerate_bucket(&self, prevouts: &[Amount]) -> Result<FeeBucket, Error> {
+        Ok(FeeBucket::from_feerate(self.feerate(prevouts)?))
+    }
+
+    // A locktime of 0 places no constraint on the transaction, and any other
+    // locktime is ignored when every input has a final (0xffffffff) sequence
+    pub fn locktime_is_enforced(&self) -> bool {
+        self.lock_time != 0 && self.inputs.iter().any(|input| input.sequence != 0xffffffff)
+    }
+
+    // Like Bitcoin Core, checks both each output and their total against MAX_MONEY
+    pub fn has_valid_amounts(&self) -> bool {
+        let mut total: u64 = 0;
+        for output in self.outputs.iter() {
+            if !output.is_valid_amount() {
+                return false;
+            }
+            total += output.amount.0;
+            if total > MAX_MONEY {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Spending the same outpoint twice makes the transaction invalid (Bitcoin
+    // Core's bad-txns-inputs-duplicate)
+    pub fn has_duplicate_inputs(&self) -> bool {
+        let mut seen = HashSet::new();
+        !self.inputs.iter().all(|input| seen.insert(input.previous_output))
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].is_coinbase()
+    }
+
+    // BIP141: a segwit coinbase commits to the wtxids of the block's transactions in
+    // an OP_RETURN output starting with 0xaa21a9ed, and its single witness item (the
+    // witness reserved value) is hashed together with them. If several outputs match
+    // the one with the highest index is the commitment.
+    pub fn witness_commitment(&self) -> Option<[u8; 32]> {
+        if !self.is_coinbase() {
+            return None;
+        }
+        self.outputs.iter().rev().find_map(|output| {
+            let script_pubkey = hex::decode(&output.script_pubkey).ok()?;
+            match script_pubkey.strip_prefix(&WITNESS_COMMITMENT_HEADER) {
+                Some(commitment) if commitment.len() >= 32 => commitment[..32].try_into().ok(),
+                _ => None,
+            }
+        })
+    }
+
+    // The distinct ways the inputs are spent
+    pub fn input_types(&self) -> BTreeSet<SpendType> {
+        self.inputs.iter().map(|input| input.spend_type()).collect()
+    }
+
+    // Mixing spend types, like legacy and segwit inputs, hints that the inputs come
+    // from different wallets or that the wallet is one that mixes them
+    pub fn input_types_uniform(&self) -> bool {
+        self.input_types().len() <= 1
+    }
+
+    // The witness versions of the outputs paying to witness programs, e.g. {0, 1}
+    // for a transaction with both P2WPKH and P2TR outputs
+    pub fn segwit_versions_used(&self) -> BTreeSet<u8> {
+        self.outputs.iter().filter_map(|output| output.witness_version()).collect()
+    }
+
+    // BIP141: a coinbase's witness, if it has one, must be a single 32 byte item (the
+    // witness reserved value). A coinbase without a witness is fine as long as its
+    // block doesn't commit to witnesses, which can't be checked from here.
+    // None for anything other than a coinbase.
+    pub fn coinbase_witness_valid(&self) -> Option<bool> {
+        if !self.is_coinbase() {
+            return None;
+        }
+        let witness = &self.inputs[0].witness;
+        Some(witness.is_empty() || matches!(witness.items(), [item] if item.len() == 32))
+    }
+
+    // Everything suspicious about the transaction in one place, in the order the
+    // fields appear in the transaction
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = vec![];
+        if self.version.warning().is_some() {
+            warnings.push(Warning::NonStandardVersion(self.version.as_i32()));
+        }
+        if self.has_duplicate_inputs() {
+            warnings.push(Warning::DuplicateInputs);
+        }
+        for (index, input) in self.inputs.iter().enumerate() {
+            // a coinbase scriptSig isn't executed, so anything goes there
+            if !input.is_coinbase() && input.has_minimal_pushes() == Some(false) {
+                warnings.push(Warning::NonMinimalPush { input: index });
+            }
+        }
+        if self.coinbase_witness_valid() == Some(false) {
+            warnings.push(Warning::InvalidCoinbaseWitness);
+        }
+        if !self.has_valid_amounts() {
+            warnings.push(Warning::AmountsExceedSupply);
+        }
+        if !self.is_relayable_size() {
+            warnings.push(Warning::BelowMinRelaySize);
+        }
+        warnings
+    }
+
+    pub fn contains_op_return(&self) -> bool {
+        self.outputs.iter().any(|output| output.op_return_data().is_some())
+    }
+
+    // The data carried by each OP_RETURN output, in output order
+    pub fn op_return_payloads(&self) -> Vec<Vec<u8>> {
+        self.outputs.iter().filter_map(|output| output.op_return_data()).collect()
+    }
+
+    // The first output paying to the scriptPubKey, with its index
+    pub fn find_output_to(&self, script_pubkey_hex: &str) -> Option<(usize, &TxOut)> {
+        self.outputs.iter().enumerate().find(|(_, output)| output.script_eq(script_pubkey_hex))
+    }
+
+    pub fn pays_to_script(&self, script_pubkey_hex: &str) -> bool {
+        self.find_output_to(script_pubkey_hex).is_some()
+    }
+
+    // Each output with its index and script type, so outputs can be filtered by type
+    // without classifying their scripts more than once
+    pub fn outputs_classified(&self) -> impl Iterator<Item = (usize, &TxOut, ScriptType)> {
+        self.outputs.iter().enumerate().map(|(i, output)| (i, output, output.script_type()))
+    }
+
+    // The address each output pays, in output order, or None for outputs
+    // without one such as OP_RETURN and non-standard scripts
+    pub fn output_addresses(&self, network: Network) -> Vec<Option<String>> {
+        self.outputs.iter().map(|output| output.address(network)).collect()
+    }
+
+    pub fn locktime_is_timestamp(&self) -> bool {
+        self.lock_time >= LOCKTIME_THRESHOLD
+    }
+
+    // The locktime as an ISO-8601 UTC date, when it's a timestamp rather than a block height
+    #[cfg(feature = "resolve-time")]
+    pub fn locktime_date(&self) -> Option<String> {
+        use time::format_description::well_known::Rfc3339;
+
+        if !self.locktime_is_timestamp() {
+            return None;
+        }
+        let date = time::OffsetDateTime::from_unix_timestamp(self.lock_time as i64).ok()?;
+        date.format(&Rfc3339).ok()
+    }
+
+    // BIP69 sorts inputs by previous txid (as displayed) and then vout,
+    // and outputs by amount and then scriptPubKey bytes
+    pub fn is_bip69_sorted(&self) -> bool {
+        let inputs_sorted = self.inputs.windows(2).all(|pair| {
+            let a = (pair[0].previous_output.txid.to_display_bytes(), pair[0].previous_output.vout);
+            let b = (pair[1].previous_output.txid.to_display_bytes(), pair[1].previous_output.vout);
+            a <= b
+        });
+        let outputs_sorted = self.outputs.windows(2).all(|pair| {
+            let a = (pair[0].amount.0, hex::decode(&pair[0].script_pubkey).unwrap_or_default());
+            let b = (pair[1].amount.0, hex::decode(&pair[1].script_pubkey).unwrap_or_default());
+            a <= b
+        });
+        inputs_sorted && outputs_sorted
+    }
+}
+
+// Locktimes below this are block heights and the rest are unix timestamps
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+// An absolute locktime, whether a transaction's or one required by OP_CHECKLOCKTIMEVERIFY
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockTime {
+    Height(u32),
+    // a unix timestamp
+    Time(u32),
+}
+
+impl LockTime {
+    pub fn from_consensus(value: u32) -> LockTime {
+        if value < LOCKTIME_THRESHOLD {
+            LockTime::Height(value)
+        } else {
+            LockTime::Time(value)
+        }
+    }
+}
+
+// The flag follows the 4 byte version and the 0x00 marker
+pub const SEGWIT_FLAG_OFFSET: usize = 5;
+
+// The smallest an input can be: a 36 byte outpoint, an empty scriptSig and the sequence
+pub const MIN_TXIN_SIZE: usize = 41;
+// An amount and an empty scriptPubKey
+pub const MIN_TXOUT_SIZE: usize = 9;
+
+// A transaction has to fit in a block, which has at most this many bytes
+// outside of witnesses. Bounds how much is allocated up front for the counts and
+// lengths read from the transaction, which could be anything.
+const MAX_BLOCK_BASE_SIZE: usize = 1_000_000;
+
+// Reads len bytes. Lengths too big for a block grow the buffer as the bytes
+// arrive rather than allocating all of it up front.
+fn read_bytes<R: Read>(r: &mut R, len: u64) -> Result<Vec<u8>, Error> {
+    if len <= MAX_BLOCK_BASE_SIZE as u64 {
+        let mut buffer = vec![0; len as usize];
+        r.read_exact(&mut buffer).map_err(Error::Io)?;
+        return Ok(buffer);
+    }
+    let mut buffer = Vec::with_capacity(MAX_BLOCK_BASE_SIZE);
+    r.by_ref().take(len).read_to_end(&mut buffer).map_err(Error::Io)?;
+    if (buffer.len() as u64) < len {
+        return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "failed to fill whole buffer")));
     }
+    Ok(buffer)
+}
+
+// Reads the bytes after an unsupported segwit flag as if the flag were 1, giving
+// the input count and then the output count for as far as they can be read
+pub fn infer_counts_after_flag(mut bytes: &[u8]) -> (Option<u64>, Option<u64>) {
+    let Ok(inputs) = Vec::<TxIn>::consensus_decode(&mut bytes) else {
+        return (None, None);
+    };
+    let outputs = Vec::<TxOut>::consensus_decode(&mut bytes).ok();
+    (Some(inputs.len() as u64), outputs.map(|outputs| outputs.len() as u64))
 }
 
+// OP_RETURN, a push of 36 bytes, then the 4 byte commitment tag
+const WITNESS_COMMITMENT_HEADER: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
 impl Serialize for Transaction {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut tx = serializer.serialize_struct("Transaction", 5)?;
+        let coinbase_witness_valid = self.coinbase_witness_valid();
+        let warnings = self.warnings();
+        let len = 10 + coinbase_witness_valid.is_some() as usize + !warnings.is_empty() as usize;
+        let mut tx = serializer.serialize_struct("Transaction", len)?;
         tx.serialize_field("transaction_id", &self.txid())?;
         tx.serialize_field("version", &self.version)?;
+        tx.serialize_field("size", &self.total_size())?;
+        tx.serialize_field("vsize", &self.vsize())?;
+        tx.serialize_field("weight", &self.weight())?;
         tx.serialize_field("inputs", &self.inputs)?;
         tx.serialize_field("outputs", &self.outputs)?;
         tx.serialize_field("locktime", &self.lock_time)?;
+        tx.serialize_field("locktime_enforced", &self.locktime_is_enforced())?;
+        tx.serialize_field("bip69", &self.is_bip69_sorted())?;
+        if let Some(valid) = coinbase_witness_valid {
+            tx.serialize_field("coinbase_witness_valid", &valid)?;
+        }
+        if !warnings.is_empty() {
+            tx.serialize_field("warnings", &warnings)?;
+        }
         tx.end()
     }
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Txid(pub [u8; 32]);
 
 impl Txid {
     fn new(data: Vec<u8>) -> Txid {
-        let mut hasher = Sha256::new();
-        hasher.update(&data);
-        let hash1 = hasher.finalize();
+        Txid(hashes::sha256d(&data))
+    }
+
+    // Txids are displayed in the reverse of the order they're hashed and serialized in
+    pub fn to_display_bytes(&self) -> [u8; 32] {
+        let mut bytes = self.0;
+        bytes.reverse();
+        bytes
+    }
+
+    // The order the hash comes out of sha256d and is written in outpoints
+    pub fn to_internal_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    // Parses a txid as shown by block explorers and Bitcoin Core
+    pub fn from_display_hex(s: &str) -> Result<Txid, Error> {
+        let mut bytes = Txid::from_internal_hex(s)?.0;
+        bytes.reverse();
+        Ok(Txid(bytes))
+    }
+
+    // Parses a txid as it appears in raw transaction bytes
+    pub fn from_internal_hex(s: &str) -> Result<Txid, Error> {
+        let bytes: [u8; 32] = hex::decode(s)
+            .map_err(|_| Error::ParseFailed("invalid txid hex"))?
+            .try_into()
+            .map_err(|_| Error::ParseFailed("txid must be 32 bytes"))?;
+        Ok(Txid(bytes))
+    }
+}
+
+impl fmt::Display for Txid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.to_display_bytes()))
+    }
+}
 
-        let mut hasher = Sha256::new();
-        hasher.update(hash1);
-        let hash2 = hasher.finalize();
+impl FromStr for Txid {
+    type Err = Error;
 
-        Txid(hash2.into())
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Txid::from_display_hex(s)
     }
 }
 
 impl Serialize for Txid {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
-        let mut bytes = self.0.clone();
-        bytes.reverse();
-        s.serialize_str(&hex::encode(bytes))
+        s.serialize_str(&self.to_string())
     }
 }
 
-#[derive(Debug, Serialize)]
+// A reference to a specific output of a previous transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutPoint {
+    pub txid: Txid,
+    pub vout: u32,
+}
+
+impl OutPoint {
+    // Coinbase inputs don't spend anything, so they reference an all zero txid and vout 0xFFFFFFFF
+    pub fn is_null(&self) -> bool {
+        self.txid == Txid([0; 32]) && self.vout == u32::MAX
+    }
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.txid, self.vout)
+    }
+}
+
+impl FromStr for OutPoint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (txid, vout) = s.split_once(':').ok_or(Error::ParseFailed("outpoint must be txid:vout"))?;
+        Ok(OutPoint {
+            txid: txid.parse()?,
+            vout: vout.parse().map_err(|_| Error::ParseFailed("invalid vout"))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct Version(pub u32);
 
-#[derive(Debug)]
+// Bitcoin Core's TX_MAX_STANDARD_VERSION, version 3 being the TRUC policy from BIP431
+pub const MAX_STANDARD_VERSION: i32 = 3;
+
+pub const MIN_STANDARD_TX_NONWITNESS_SIZE: usize = 65;
+
+impl Version {
+    // Bitcoin Core stores the version as a signed int32, so versions with the
+    // high bit set are negative
+    pub fn as_i32(&self) -> i32 {
+        self.0 as i32
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        let version = self.as_i32();
+        if version < 0 {
+            Some(format!("version {} is negative", version))
+        } else if version > MAX_STANDARD_VERSION {
+            Some(format!("version {} is above the standard maximum of {}", version, MAX_STANDARD_VERSION))
+        } else {
+            None
+        }
+    }
+}
+
+// The lowest fee rate, in sat/vB, of each bucket above VeryLow. Each bucket
+// includes its lower bound, so 5 sat/vB is Medium.
+pub const FEE_BUCKET_LOW: f64 = 2.0;
+pub const FEE_BUCKET_MEDIUM: f64 = 5.0;
+pub const FEE_BUCKET_HIGH: f64 = 20.0;
+pub const FEE_BUCKET_VERY_HIGH: f64 = 50.0;
+
+// A coarse grouping of fee rates, for binning transactions the way mempool
+// visualizations do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FeeBucket {
+    VeryLow,
+    Low,
+    Medium,
+    High,
+    VeryHigh,
+}
+
+impl FeeBucket {
+    pub fn from_feerate(sat_per_vbyte: f64) -> FeeBucket {
+        if sat_per_vbyte >= FEE_BUCKET_VERY_HIGH {
+            FeeBucket::VeryHigh
+        } else if sat_per_vbyte >= FEE_BUCKET_HIGH {
+            FeeBucket::High
+        } else if sat_per_vbyte >= FEE_BUCKET_MEDIUM {
+            FeeBucket::Medium
+        } else if sat_per_vbyte >= FEE_BUCKET_LOW {
+            FeeBucket::Low
+        } else {
+            FeeBucket::VeryLow
+        }
+    }
+}
+
+// Something suspicious about a transaction, from Transaction::warnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    // negative or above MAX_STANDARD_VERSION
+    NonStandardVersion(i32),
+    DuplicateInputs,
+    // the input's scriptSig has a push that could be encoded more compactly
+    NonMinimalPush { input: usize },
+    InvalidCoinbaseWitness,
+    AmountsExceedSupply,
+    BelowMinRelaySize,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::NonStandardVersion(version) => match Version(version as u32).warning() {
+                Some(warning) => write!(f, "{}", warning),
+                None => write!(f, "version {} is non-standard", version),
+            },
+            Warning::DuplicateInputs => write!(f, "spends the same outpoint more than once"),
+            Warning::NonMinimalPush { input } => write!(f, "input {} has a scriptSig push that isn't minimally encoded", input),
+            Warning::InvalidCoinbaseWitness => write!(f, "the coinbase witness isn't a single 32 byte item"),
+            Warning::AmountsExceedSupply => write!(f, "output amounts exceed the 21 million BTC supply"),
+            Warning::BelowMinRelaySize => write!(
+                f, "the size without witnesses is below the {} byte minimum for relay", MIN_STANDARD_TX_NONWITNESS_SIZE,
+            ),
+        }
+    }
+}
+
+// Written as its message
+impl Serialize for Warning {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+// How an input is being spent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SpendType {
+    Coinbase,
+    // everything is in the scriptSig
+    Legacy,
+    NestedSegwit,
+    NativeSegwit,
+    TaprootKeyPath,
+    TaprootScriptPath,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TxIn {
-    pub previous_txid: Txid,
-    pub previous_vout: u32,
+    pub previous_output: OutPoint,
     pub script_sig: String,
     pub sequence: u32,
     pub witness: Witness,
 }
 
+impl TxIn {
+    // An input with a final sequence and no witness
+    pub fn new(previous_output: OutPoint, script_sig: String) -> TxIn {
+        TxIn {
+            previous_output,
+            script_sig,
+            sequence: 0xffffffff,
+            witness: Witness::new(),
+        }
+    }
+
+    // Size of the input without its witness
+    pub fn serialized_len(&self) -> usize {
+        let script_len = self.script_sig.len() / 2;
+        36 + CompactSize(script_len as u64).serialized_len() + script_len + 4
+    }
+
+    // What the input adds to the transaction's weight: 4 units per non-witness byte
+    // and 1 per witness byte. An empty witness isn't counted, since in a legacy
+    // transaction it isn't serialized at all. In a segwit one it's a single 00 byte,
+    // which Transaction::input_weights adds.
+    pub fn weight(&self) -> usize {
+        let witness = if self.witness.is_empty() { 0 } else { self.witness.serialized_len() };
+        self.serialized_len() * 4 + witness
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.previous_output.is_null()
+    }
+
+    // Miners are free to put anything in a coinbase scriptSig and often include a
+    // message. Returns the pushes made up entirely of printable characters or, if the
+    // scriptSig isn't push only, every run of at least 4 printable characters.
+    pub fn coinbase_message(&self) -> Option<String> {
+        if !self.is_coinbase() {
+            return None;
+        }
+        let script_sig = hex::decode(&self.script_sig).ok()?;
+        let is_printable = |b: &u8| b.is_ascii_graphic() || *b == b' ';
+
+        let parts: Vec<&[u8]> = match script::push_data(&script_sig) {
+            Some(pushes) => pushes.into_iter()
+                .filter(|push| !push.is_empty() && push.iter().all(is_printable))
+                .collect(),
+            None => script_sig.split(|b| !is_printable(b))
+                .filter(|run| run.len() >= 4)
+                .collect(),
+        };
+        if parts.is_empty() {
+            return None;
+        }
+        let message: Vec<String> = parts.iter()
+            .map(|part| String::from_utf8_lossy(part).into_owned())
+            .collect();
+        Some(message.join(" "))
+    }
+
+    // The signature of a P2PKH or P2WPKH spend, which is the first of the two
+    // items pushed in the scriptSig or placed in the witness
+    pub fn signature(&self) -> Option<Vec<u8>> {
+        if !self.witness.is_empty() {
+            return match self.witness.content.as_slice() {
+                [sig, _pubkey] => Some(sig.clone()),
+                _ => None,
+            };
+        }
+        let script_sig = hex::decode(&self.script_sig).ok()?;
+        match script::push_data(&script_sig)?.as_slice() {
+            [sig, _pubkey] => Some(sig.to_vec()),
+            _ => None,
+        }
+    }
+
+    // For a P2WSH spend the last witness item is the script being satisfied.
+    // We can't see the output being spent, so anything with more than one item
+    // that isn't shaped like a P2WPKH spend (signature and public key) is treated as one.
+    // A taproot spend ends with a control block rather than a script, so has none.
+    pub fn witness_script(&self) -> Option<&[u8]> {
+        if matches!(self.spend_type(), SpendType::TaprootKeyPath | SpendType::TaprootScriptPath) {
+            return None;
+        }
+        match self.witness.stack() {
+            [] | [_] => None,
+            [_sig, pubkey] if is_compressed_pubkey(pubkey) => None,
+            [.., script] => Some(script),
+        }
+    }
+
+    // We can't see the output being spent, so the spend type is inferred from the
+    // scriptSig and witness
+    pub fn spend_type(&self) -> SpendType {
+        if self.is_coinbase() {
+            return SpendType::Coinbase;
+        }
+        let stack = self.witness.stack();
+        if stack.is_empty() {
+            return SpendType::Legacy;
+        }
+        if self.is_nested_segwit() {
+            return SpendType::NestedSegwit;
+        }
+        let is_schnorr_signature = |item: &Vec<u8>| item.len() == 64 || item.len() == 65;
+        // a script path spend ends with a control block of 33 + 32m bytes starting with the leaf version
+        let is_control_block = |item: &Vec<u8>| item.len() >= 33 && (item.len() - 33).is_multiple_of(32) && item[0] & 0xfe == 0xc0;
+        match stack {
+            [signature] if is_schnorr_signature(signature) => SpendType::TaprootKeyPath,
+            [_, .., control_block] if is_control_block(control_block) => SpendType::TaprootScriptPath,
+            _ => SpendType::NativeSegwit,
+        }
+    }
+
+    // P2SH wrapped segwit: the scriptSig only pushes the redeem script, which is
+    // itself a v0 witness program, and the signatures are in the witness
+    pub fn is_nested_segwit(&self) -> bool {
+        if self.witness.is_empty() {
+            return false;
+        }
+        let script_sig = match hex::decode(&self.script_sig) {
+            Ok(script_sig) => script_sig,
+            Err(_) => return false,
+        };
+        match script::push_data(&script_sig).as_deref() {
+            Some([redeem_script]) => script::is_v0_witness_program(redeem_script),
+            _ => false,
+        }
+    }
+
+    // For a P2SH spend the last push in the scriptSig is the redeem script. We can't
+    // see the output being spent, so the last push is treated as one when it parses
+    // as a script ending in an opcode (or is a witness program) and isn't a public key.
+    pub fn redeem_script(&self) -> Option<Vec<u8>> {
+        if self.is_coinbase() {
+            return None;
+        }
+        let script_sig = hex::decode(&self.script_sig).ok()?;
+        let last = *script::push_data(&script_sig)?.last()?;
+        if last.is_empty() || is_compressed_pubkey(last) || is_uncompressed_pubkey(last) {
+            return None;
+        }
+        if script::is_witness_program(last) {
+            return Some(last.to_vec());
+        }
+        match script::instructions(last).last()? {
+            Ok(Instruction::Op(_)) if script::instructions(last).all(|i| i.is_ok()) => Some(last.to_vec()),
+            _ => None,
+        }
+    }
+
+    // OP_CHECKMULTISIG pops one more item than it uses, so multisig scriptSigs start
+    // with a dummy OP_0 before the signatures. For a P2SH spend the redeem script at
+    // the end isn't a signature either.
+    pub fn multisig_signatures(&self) -> Option<Vec<Vec<u8>>> {
+        let script_sig = hex::decode(&self.script_sig).ok()?;
+        let pushes = script::push_data(&script_sig)?;
+        let (dummy, rest) = pushes.split_first()?;
+        if !dummy.is_empty() {
+            return None;
+        }
+        let signatures = match self.redeem_script() {
+            Some(_) => &rest[..rest.len().saturating_sub(1)],
+            None => rest,
+        };
+        if signatures.is_empty() {
+            return None;
+        }
+        Some(signatures.iter().map(|signature| signature.to_vec()).collect())
+    }
+
+    pub fn sighash_type(&self) -> Option<u32> {
+        signature::parse_sighash_type(&self.signature()?)
+    }
+
+    // Returns None if the scriptSig isn't valid hex
+    pub fn has_minimal_pushes(&self) -> Option<bool> {
+        let script_sig = hex::decode(&self.script_sig).ok()?;
+        Some(Script::from_bytes(script_sig).uses_minimal_pushes())
+    }
+
+    // Any item in the scriptSig or witness that looks like a signature
+    // but doesn't follow the strict DER and low-S rules
+    pub fn has_non_standard_signature(&self) -> bool {
+        let looks_like_signature = |item: &[u8]| item.len() >= 9 && item[0] == 0x30;
+        if !self.witness.is_empty() {
+            return self.witness.content.iter()
+                .any(|item| looks_like_signature(item) && !signature::is_valid_der_signature(item));
+        }
+        let script_sig = match hex::decode(&self.script_sig) {
+            Ok(script_sig) => script_sig,
+            Err(_) => return false,
+        };
+        match script::push_data(&script_sig) {
+            Some(pushes) => pushes.into_iter()
+                .any(|item| looks_like_signature(item) && !signature::is_valid_der_signature(item)),
+            None => false,
+        }
+    }
+}
+
+fn is_compressed_pubkey(bytes: &[u8]) -> bool {
+    bytes.len() == 33 && (bytes[0] == 0x02 || bytes[0] == 0x03)
+}
+
+fn is_uncompressed_pubkey(bytes: &[u8]) -> bool {
+    bytes.len() == 65 && bytes[0] == 0x04
+}
+
 impl Serialize for TxIn {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
         let mut txin = s.serialize_struct("TxIn", 4)?;
-        txin.serialize_field("txid", &self.previous_txid)?;
-        txin.serialize_field("vout", &self.previous_vout)?;
+        txin.serialize_field("txid", &self.previous_output.txid)?;
+        txin.serialize_field("vout", &self.previous_output.vout)?;
 
         if self.witness.is_empty() {
             txin.serialize_field("scriptSig", &self.script_sig)?;
         } else {
             txin.serialize_field("txinwitness", &self.witness)?;
+            if let Some(annex) = self.witness.annex() {
+                txin.serialize_field("annex", &hex::encode(annex))?;
+            }
+            if let Some(witness_script) = self.witness_script() {
+                let witness_script = Script::from_bytes(witness_script.to_vec());
+                txin.serialize_field("witness_script", &witness_script.asm())?;
+            }
+        }
+
+        if let Some(redeem_script) = self.redeem_script() {
+            txin.serialize_field("redeem_script", &Script::from_bytes(redeem_script).asm())?;
         }
 
         txin.serialize_field("sequence", &self.sequence)?;
+        if let Some(sighash_type) = self.sighash_type() {
+            txin.serialize_field("sighash", &signature::sighash_type_to_string(sighash_type))?;
+        }
+        if self.is_nested_segwit() {
+            txin.serialize_field("nested_segwit", &true)?;
+        }
+        if self.has_non_standard_signature() {
+            txin.serialize_field("non_standard_signature", &true)?;
+        }
+        // a coinbase scriptSig is arbitrary data that isn't subject to the rule
+        if !self.is_coinbase() && self.has_minimal_pushes() == Some(false) {
+            txin.serialize_field("non_minimal_push", &true)?;
+        }
         txin.end()
     } 
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Witness {
     content: Vec<Vec<u8>>,
 }
@@ -121,33 +952,232 @@ impl Witness {
         Witness { content: vec![] }
     }
 
+    pub fn from_items(items: Vec<Vec<u8>>) -> Self {
+        Witness { content: items }
+    }
+
+    // A witness with a single zero-length item is not empty, only one with no items is
     fn is_empty(&self) -> bool {
         self.content.is_empty()
     }
+
+    // The number of items followed by each item prefixed with its length
+    pub fn serialized_len(&self) -> usize {
+        let items: usize = self.content.iter()
+            .map(|item| CompactSize(item.len() as u64).serialized_len() + item.len())
+            .sum();
+        CompactSize(self.content.len() as u64).serialized_len() + items
+    }
+
+    // BIP341: if there are at least two witness items and the last one starts
+    // with 0x50, that item is the annex and isn't part of the script's stack
+    pub fn annex(&self) -> Option<&[u8]> {
+        match self.content.as_slice() {
+            [_, .., last] if last.first() == Some(&ANNEX_TAG) => Some(last),
+            _ => None,
+        }
+    }
+
+    // Every witness item in the order they were serialized, including any annex
+    pub fn items(&self) -> &[Vec<u8>] {
+        &self.content
+    }
+
+    // The witness items excluding the annex, if there is one
+    pub fn stack(&self) -> &[Vec<u8>] {
+        match self.annex() {
+            Some(_) => &self.content[..self.content.len() - 1],
+            None => &self.content,
+        }
+    }
+
+    // Serializes with the size of each item, for seeing where the witness weight goes
+    pub fn verbose(&self) -> VerboseWitness<'_> {
+        VerboseWitness(self)
+    }
 }
 
+const ANNEX_TAG: u8 = 0x50;
+
+// Each stack item is rendered as a hex string. A zero-length item (such as the
+// dummy element consumed by OP_CHECKMULTISIG) becomes "", which can't be confused
+// with a missing item since the array always has one entry per stack item.
+// The annex isn't a stack item so it's left for the input to serialize separately.
 impl Serialize for Witness {
     fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
         use serde::ser::SerializeSeq;
 
-        let mut seq = s.serialize_seq(Some(self.content.len()))?;
+        let stack = self.stack();
+        let mut seq = s.serialize_seq(Some(stack.len()))?;
 
-        for elem in self.content.iter() {
-            seq.serialize_element(&hex::encode(&elem))?;
+        for elem in stack.iter() {
+            seq.serialize_element(&hex::encode(elem))?;
         }
         seq.end()
     }
 }
 
-#[derive(Debug, Serialize)]
+// Every item (including any annex, since it's weighed too) as a hex string and
+// its size, plus witness_bytes for the whole serialized witness with its length prefixes
+pub struct VerboseWitness<'a>(&'a Witness);
+
+impl Serialize for VerboseWitness<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Item {
+            hex: String,
+            size: usize,
+        }
+
+        let items: Vec<Item> = self.0.items().iter()
+            .map(|item| Item { hex: hex::encode(item), size: item.len() })
+            .collect();
+        let mut witness = s.serialize_struct("Witness", 2)?;
+        witness.serialize_field("items", &items)?;
+        witness.serialize_field("witness_bytes", &self.0.serialized_len())?;
+        witness.end()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TxOut {
     pub amount: Amount,
     pub script_pubkey: String,
 }
 
+// Bitcoin Core's default -dustrelayfee, in satoshis per 1000 virtual bytes
+pub const DEFAULT_DUST_RELAY_FEE: u64 = 3000;
+
+// No amount can be larger than the 21 million BTC that will ever exist
+pub const MAX_MONEY: u64 = 21_000_000 * 100_000_000;
+
+impl TxOut {
+    pub fn new(amount: Amount, script_pubkey: String) -> TxOut {
+        TxOut { amount, script_pubkey }
+    }
+
+    // Whether the scriptPubKey is the one given as hex, in either case
+    pub fn script_eq(&self, other_hex: &str) -> bool {
+        self.script_pubkey.eq_ignore_ascii_case(other_hex)
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        let script_len = self.script_pubkey.len() / 2;
+        8 + CompactSize(script_len as u64).serialized_len() + script_len
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        match hex::decode(&self.script_pubkey) {
+            Ok(script) => script::script_type(&script),
+            Err(_) => ScriptType::NonStandard,
+        }
+    }
+
+    pub fn witness_version(&self) -> Option<u8> {
+        let script = hex::decode(&self.script_pubkey).ok()?;
+        Script::from_bytes(script).witness_program().map(|(version, _)| version)
+    }
+
+    pub fn address(&self, network: Network) -> Option<String> {
+        let script = hex::decode(&self.script_pubkey).ok()?;
+        address::from_script_pubkey_for(&script, network)
+    }
+
+    // Outputs have no witness data, so every byte counts for 4 weight units
+    pub fn weight(&self) -> usize {
+        self.serialized_len() * 4
+    }
+
+    // Mirrors Bitcoin Core's GetDustThreshold: the output is dust if it's worth
+    // less than the fee it would cost to create and later spend it. Returns None
+    // if the scriptPubKey isn't valid hex.
+    pub fn dust_threshold(&self, dust_relay_fee_sat_per_kvb: u64) -> Option<u64> {
+        let script = hex::decode(&self.script_pubkey).ok()?;
+        if script::is_unspendable(&script) {
+            return Some(0);
+        }
+
+        let mut size = 0;
+        size += 8; // amount
+        size += CompactSize(script.len() as u64).serialized_len() as u64;
+        size += script.len() as u64;
+
+        // size of the input that would spend this output
+        // (outpoint, scriptSig length, sequence and the spending scriptSig itself)
+        if script::is_witness_program(&script) {
+            // 107 bytes of witness data are discounted by the witness scale factor
+            size += 32 + 4 + 1 + (107 / 4) + 4;
+        } else {
+            size += 32 + 4 + 1 + 107 + 4;
+        }
+
+        let fee = size * dust_relay_fee_sat_per_kvb / 1000;
+        if fee == 0 && dust_relay_fee_sat_per_kvb > 0 {
+            Some(1)
+        } else {
+            Some(fee)
+        }
+    }
+
+    pub fn value_btc(&self) -> f64 {
+        self.amount.btc()
+    }
+
+    // An output without a threshold can't be judged, so isn't counted as dust
+    pub fn is_dust(&self, dust_relay_fee_sat_per_kvb: u64) -> bool {
+        self.dust_threshold(dust_relay_fee_sat_per_kvb).is_some_and(|threshold| self.amount.0 < threshold)
+    }
+
+    pub fn is_valid_amount(&self) -> bool {
+        self.amount.0 <= MAX_MONEY
+    }
+
+    // The locktime a spending transaction needs when the scriptPubKey starts with
+    // `<n> OP_CHECKLOCKTIMEVERIFY OP_DROP`. A negative n or one too big for a
+    // locktime can never be satisfied, so gives None.
+    pub fn absolute_timelock(&self) -> Option<LockTime> {
+        let script = Script::from_bytes(hex::decode(&self.script_pubkey).ok()?);
+        match script.timelock_condition()? {
+            TimelockCondition::CheckLockTimeVerify(value) => u32::try_from(value).ok().map(LockTime::from_consensus),
+            TimelockCondition::CheckSequenceVerify(_) => None,
+        }
+    }
+
+    // The pushes following OP_RETURN joined together. Returns None if this isn't an
+    // OP_RETURN output or something other than a push follows the OP_RETURN.
+    pub fn op_return_data(&self) -> Option<Vec<u8>> {
+        let script = hex::decode(&self.script_pubkey).ok()?;
+        if !script::is_op_return(&script) {
+            return None;
+        }
+        Some(script::push_data(&script[1..])?.concat())
+    }
+}
+
+impl Serialize for TxOut {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut txout = s.serialize_struct("TxOut", 3)?;
+        txout.serialize_field("amount", &self.amount)?;
+        txout.serialize_field("script_pubkey", &self.script_pubkey)?;
+        txout.serialize_field("dust", &self.is_dust(DEFAULT_DUST_RELAY_FEE))?;
+        txout.end()
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct CompactSize(pub u64);
 
+impl CompactSize {
+    pub fn serialized_len(&self) -> usize {
+        match self.0 {
+            0..=0xFC => 1,
+            0xFD..=0xFFFF => 3,
+            0x10000..=0xFFFFFFFF => 5,
+            _ => 9,
+        }
+    }
+}
+
 pub trait Encodable {
     fn consensus_encode<W: Write>(&self, writer: &mut W) -> Result<usize, Error>;
 }
@@ -192,7 +1222,7 @@ impl Encodable for [u8; 32] {
 
 impl Encodable for String {
     fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
-        let b = hex::decode(self).expect("should be a valid hex string");
+        let b = hex::decode(self).map_err(|_| Error::ParseFailed("invalid script hex"))?;
         let compact_size_len = CompactSize(b.len() as u64).consensus_encode(w)?;
         let b_len = w.write(&b).map_err(Error::Io)?;
         Ok(compact_size_len + b_len)
@@ -232,28 +1262,43 @@ impl Encodable for Version {
     }
 }
 
-impl Encodable for Vec<TxIn> {
+// A list is encoded as its length followed by each element
+impl<T: Encodable> Encodable for [T] {
     fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
         let mut len = 0;
         len += CompactSize(self.len() as u64).consensus_encode(w)?;
-        for tx in self.iter() {
-            len += tx.consensus_encode(w)?;
+        for item in self.iter() {
+            len += item.consensus_encode(w)?;
         }
         Ok(len)
     }
 }
 
+impl Encodable for Vec<TxIn> {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
+        self.as_slice().consensus_encode(w)
+    }
+}
+
 impl Encodable for Txid {
     fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
-        Ok(self.0.consensus_encode(w)?)
+        self.0.consensus_encode(w)
+    }
+}
+
+impl Encodable for OutPoint {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
+        let mut len = 0;
+        len += self.txid.consensus_encode(w)?;
+        len += self.vout.consensus_encode(w)?;
+        Ok(len)
     }
 }
 
 impl Encodable for TxIn {
     fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
         let mut len = 0;
-        len += self.previous_txid.consensus_encode(w)?;
-        len += self.previous_vout.consensus_encode(w)?;
+        len += self.previous_output.consensus_encode(w)?;
         len += self.script_sig.consensus_encode(w)?;
         len += self.sequence.consensus_encode(w)?;
         Ok(len)
@@ -262,12 +1307,7 @@ impl Encodable for TxIn {
 
 impl Encodable for Vec<TxOut> {
     fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
-        let mut len = 0;
-        len += CompactSize(self.len() as u64).consensus_encode(w)?;
-        for tx in self.iter() {
-            len += tx.consensus_encode(w)?;
-        }
-        Ok(len)
+        self.as_slice().consensus_encode(w)
     }
 }
 
@@ -287,6 +1327,39 @@ impl Encodable for TxOut {
     }
 }
 
+impl Encodable for Witness {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
+        let mut len = 0;
+        len += CompactSize(self.content.len() as u64).consensus_encode(w)?;
+        for item in self.content.iter() {
+            len += CompactSize(item.len() as u64).consensus_encode(w)?;
+            len += w.write(item).map_err(Error::Io)?;
+        }
+        Ok(len)
+    }
+}
+
+impl Encodable for Transaction {
+    fn consensus_encode<W: Write>(&self, w: &mut W) -> Result<usize, Error> {
+        let mut len = 0;
+        len += self.version.consensus_encode(w)?;
+        if self.is_segwit() {
+            // segwit marker and flag
+            len += 0u8.consensus_encode(w)?;
+            len += 1u8.consensus_encode(w)?;
+        }
+        len += self.inputs.consensus_encode(w)?;
+        len += self.outputs.consensus_encode(w)?;
+        if self.is_segwit() {
+            for input in self.inputs.iter() {
+                len += input.witness.consensus_encode(w)?;
+            }
+        }
+        len += self.lock_time.consensus_encode(w)?;
+        Ok(len)
+    }
+}
+
 pub trait Decodable: Sized {
     fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, Error>;
 }
@@ -326,15 +1399,13 @@ impl Decodable for u64 {
 impl Decodable for String {
     fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, Error> {
         let len = CompactSize::consensus_decode(reader)?.0;
-        let mut buffer = vec![0; len as usize];
-        reader.read_exact(&mut buffer).map_err(Error::Io)?;
-        Ok(hex::encode(buffer))
+        Ok(hex::encode(read_bytes(reader, len)?))
     }
 }
 
 impl Decodable for Version {
     fn consensus_decode<R: Read>(reader: &mut R) -> Result<Self, Error> {
-        Ok(Version(u32::consensus_decode(reader)?))
+        Ok(Version(u32::consensus_decode(reader).map_err(eof_context("version"))?))
     }
 }
 
@@ -362,10 +1433,13 @@ impl Decodable for CompactSize {
 
 impl Decodable for Vec<TxIn> {
     fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, Error> {
-        let len = CompactSize::consensus_decode(r)?.0;
-        let mut ret = Vec::with_capacity(len as usize);
-        for _ in 0..len {
-            ret.push(TxIn::consensus_decode(r)?);
+        let len = CompactSize::consensus_decode(r).map_err(eof_context("input count"))?.0;
+        let mut ret = Vec::with_capacity((len as usize).min(MAX_BLOCK_BASE_SIZE / MIN_TXIN_SIZE));
+        let mut r = CountingReader::new(r);
+        for i in 0..len as usize {
+            let start = r.position();
+            let input = TxIn::consensus_decode(&mut r);
+            ret.push(input.map_err(input_count_context(len, i, r.position() == start))?);
         }
         Ok(ret)
     }
@@ -379,13 +1453,21 @@ impl Decodable for Txid {
     }
 }
 
+impl Decodable for OutPoint {
+    fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, Error> {
+        Ok(OutPoint {
+            txid: Txid::consensus_decode(r)?,
+            vout: u32::consensus_decode(r)?,
+        })
+    }
+}
+
 impl Decodable for TxIn {
     fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, Error> {
         Ok(TxIn {
-            previous_txid: Txid::consensus_decode(r)?,
-            previous_vout: u32::consensus_decode(r)?,
-            script_sig: String::consensus_decode(r)?,
-            sequence: u32::consensus_decode(r)?,
+            previous_output: OutPoint::consensus_decode(r).map_err(eof_context("previous output"))?,
+            script_sig: String::consensus_decode(r).map_err(eof_context("scriptSig"))?,
+            sequence: u32::consensus_decode(r).map_err(eof_context("sequence"))?,
             witness: Witness::new(),
         })
     }
@@ -393,8 +1475,8 @@ impl Decodable for TxIn {
 
 impl Decodable for Vec<TxOut> {
     fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, Error> {
-        let len = CompactSize::consensus_decode(r)?.0;
-        let mut ret = Vec::with_capacity(len as usize);
+        let len = CompactSize::consensus_decode(r).map_err(eof_context("output count"))?.0;
+        let mut ret = Vec::with_capacity((len as usize).min(MAX_BLOCK_BASE_SIZE / MIN_TXOUT_SIZE));
         for _ in 0..len {
             ret.push(TxOut::consensus_decode(r)?);
         }
@@ -405,8 +1487,8 @@ impl Decodable for Vec<TxOut> {
 impl Decodable for TxOut {
     fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, Error> {
         Ok(TxOut {
-            amount: Amount::from_sat(u64::consensus_decode(r)?),
-            script_pubkey: String::consensus_decode(r)?
+            amount: Amount::from_sat(u64::consensus_decode(r).map_err(eof_context("amount"))?),
+            script_pubkey: String::consensus_decode(r).map_err(eof_context("scriptPubKey"))?
         })
     }
 }
@@ -414,12 +1496,10 @@ impl Decodable for TxOut {
 impl Decodable for Witness {
     fn consensus_decode<R: Read>(r: &mut R) -> Result<Self, Error> {
         let mut witness_items = vec![];
-        let count = u8::consensus_decode(r)?;
+        let count = CompactSize::consensus_decode(r)?.0;
         for _ in 0..count {
             let len = CompactSize::consensus_decode(r)?.0;
-            let mut buffer = vec![0; len as usize];
-            r.read_exact(&mut buffer).map_err(Error::Io)?;
-            witness_items.push(buffer);
+            witness_items.push(read_bytes(r, len)?);
         }
         Ok(Witness{ content: witness_items })
     }
@@ -430,13 +1510,13 @@ impl Decodable for Transaction {
         let version = Version::consensus_decode(r)?;
         let inputs = Vec::<TxIn>::consensus_decode(r)?;
         if inputs.is_empty() {
-            let segwit_flag = u8::consensus_decode(r)?;
+            let segwit_flag = u8::consensus_decode(r).map_err(eof_context("segwit flag"))?;
             match segwit_flag {
                 1 => {
                     let mut inputs = Vec::<TxIn>::consensus_decode(r)?;
                     let outputs = Vec::<TxOut>::consensus_decode(r)?;
                     for txin in inputs.iter_mut() {
-                        txin.witness = Witness::consensus_decode(r)?;
+                        txin.witness = Witness::consensus_decode(r).map_err(eof_context("witness"))?;
                     }
                     if !inputs.is_empty() && inputs.iter().all(|input| input.witness.is_empty()) {
                         Err(Error::ParseFailed("witness flag set but no witnesses present"))
@@ -445,12 +1525,12 @@ impl Decodable for Transaction {
                             version,
                             inputs,
                             outputs,
-                            lock_time: u32::consensus_decode(r)?,
+                            lock_time: u32::consensus_decode(r).map_err(eof_context("locktime"))?,
                         })
                     }
                 }
                 // We don't support anything else
-                x => Err(Error::UnsupportedSegwitFlag(x)),
+                flag => Err(Error::UnsupportedSegwitFlag { flag, offset: SEGWIT_FLAG_OFFSET, inputs: None, outputs: None }),
             }
         // non-segwit
         } else {
@@ -458,19 +1538,166 @@ impl Decodable for Transaction {
                 version,
                 inputs,
                 outputs: Vec::<TxOut>::consensus_decode(r)?,
-                lock_time: u32::consensus_decode(r)?,
+                lock_time: u32::consensus_decode(r).map_err(eof_context("locktime"))?,
             })
         }        
     }
 }
 
-#[derive(Debug)]
+impl Transaction {
+    // Always decodes the pre-segwit layout. An input count of zero is taken at face
+    // value instead of as the segwit marker, so the bytes after it are the outputs.
+    pub fn consensus_decode_legacy<R: Read>(r: &mut R) -> Result<Self, Error> {
+        Ok(Transaction {
+            version: Version::consensus_decode(r)?,
+            inputs: Vec::<TxIn>::consensus_decode(r)?,
+            outputs: Vec::<TxOut>::consensus_decode(r)?,
+            lock_time: u32::consensus_decode(r).map_err(eof_context("locktime"))?,
+        })
+    }
+}
+
+// The byte range each part of a transaction was decoded from
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxSpans {
+    pub version: Range<usize>,
+    pub marker_and_flag: Option<Range<usize>>,
+    pub input_count: Range<usize>,
+    pub inputs: Vec<Range<usize>>,
+    pub output_count: Range<usize>,
+    pub outputs: Vec<Range<usize>>,
+    pub witnesses: Vec<Range<usize>>,
+    pub locktime: Range<usize>,
+}
+
+impl Transaction {
+    // Decodes the same way as consensus_decode, but also records where each
+    // field starts and ends in the raw bytes
+    pub fn decode_with_spans(bytes: &[u8]) -> Result<(Transaction, TxSpans), Error> {
+        let mut r = CountingReader::new(bytes);
+
+        let version = Version::consensus_decode(&mut r)?;
+        let version_span = 0..r.position();
+
+        let mut start = r.position();
+        let mut input_count = CompactSize::consensus_decode(&mut r).map_err(eof_context("input count"))?.0;
+        let mut marker_and_flag = None;
+        if input_count == 0 {
+            match u8::consensus_decode(&mut r).map_err(eof_context("segwit flag"))? {
+                1 => {
+                    marker_and_flag = Some(start..r.position());
+                    start = r.position();
+                    input_count = CompactSize::consensus_decode(&mut r).map_err(eof_context("input count"))?.0;
+                }
+                flag => {
+                    let offset = r.position() - 1;
+                    return Err(Error::UnsupportedSegwitFlag { flag, offset, inputs: None, outputs: None });
+                }
+            }
+        }
+        let input_count_span = start..r.position();
+
+        // the count can't be trusted, but the bytes left bound how many there can be
+        let capacity = (input_count as usize).min((bytes.len() - r.position()) / MIN_TXIN_SIZE);
+        let mut inputs = Vec::with_capacity(capacity);
+        let mut input_spans = Vec::with_capacity(capacity);
+        for i in 0..input_count as usize {
+            let start = r.position();
+            let input = TxIn::consensus_decode(&mut r);
+            inputs.push(input.map_err(input_count_context(input_count, i, r.position() == start))?);
+            input_spans.push(start..r.position());
+        }
+
+        let start = r.position();
+        let output_count = CompactSize::consensus_decode(&mut r).map_err(eof_context("output count"))?.0;
+        let output_count_span = start..r.position();
+
+        let capacity = (output_count as usize).min((bytes.len() - r.position()) / MIN_TXOUT_SIZE);
+        let mut outputs = Vec::with_capacity(capacity);
+        let mut output_spans = Vec::with_capacity(capacity);
+        for _ in 0..output_count {
+            let start = r.position();
+            outputs.push(TxOut::consensus_decode(&mut r)?);
+            output_spans.push(start..r.position());
+        }
+
+        let mut witness_spans = vec![];
+        if marker_and_flag.is_some() {
+            for txin in inputs.iter_mut() {
+                let start = r.position();
+                txin.witness = Witness::consensus_decode(&mut r).map_err(eof_context("witness"))?;
+                witness_spans.push(start..r.position());
+            }
+            if !inputs.is_empty() && inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(Error::ParseFailed("witness flag set but no witnesses present"));
+            }
+        }
+
+        let start = r.position();
+        let lock_time = u32::consensus_decode(&mut r).map_err(eof_context("locktime"))?;
+        let locktime_span = start..r.position();
+
+        let transaction = Transaction { version, inputs, outputs, lock_time };
+        let spans = TxSpans {
+            version: version_span,
+            marker_and_flag,
+            input_count: input_count_span,
+            inputs: input_spans,
+            output_count: output_count_span,
+            outputs: output_spans,
+            witnesses: witness_spans,
+            locktime: locktime_span,
+        };
+        Ok((transaction, spans))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Amount(u64);
 
 impl Amount {
     pub fn from_sat(satoshi: u64) -> Amount {
         Amount(satoshi)
     }
+
+    pub fn to_sat(&self) -> u64 {
+        self.0
+    }
+
+    pub fn btc(&self) -> f64 {
+        self.to_btc()
+    }
+
+    // The exact BTC value with all 8 decimal places, e.g. 0.01500000
+    pub fn to_btc_string(&self) -> String {
+        format!("{}.{:08}", self.0 / 100_000_000, self.0 % 100_000_000)
+    }
+
+    // The exact BTC value without trailing zeros, e.g. 0.015, or 1 for a whole bitcoin
+    pub fn to_btc_string_trimmed(&self) -> String {
+        self.to_btc_string().trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+}
+
+// The signed difference between two amounts, e.g. the fee bump of a replacement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmountDelta(pub i64);
+
+impl AmountDelta {
+    // Returns None if either amount or their difference doesn't fit in an i64
+    pub fn from_diff(a: Amount, b: Amount) -> Option<AmountDelta> {
+        let a = i64::try_from(a.0).ok()?;
+        let b = i64::try_from(b.0).ok()?;
+        a.checked_sub(b).map(AmountDelta)
+    }
 }
 
 trait BitcoinValue {
@@ -488,3 +1715,492 @@ impl Serialize for Amount {
         s.serialize_f64(self.to_btc())
     }
 }
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{
+        Amount, AmountDelta, CompactSize, Decodable, Encodable, Error, LockTime, MAX_MONEY, OutPoint, Transaction, TxIn, TxOut, Txid,
+        SpendType, Version, Witness,
+        DEFAULT_DUST_RELAY_FEE,
+    };
+    use crate::script::Script;
+    use crate::test_vectors::SEGWIT_TRANSACTION_HEX;
+
+    #[test]
+    fn test_is_relayable_size() {
+        // one input with an empty scriptSig and one output, 60 bytes plus the script
+        let transaction = |script_pubkey: &str| Transaction {
+            version: Version(2),
+            inputs: vec![TxIn::new(OutPoint { txid: Txid([0; 32]), vout: 0 }, String::new())],
+            outputs: vec![TxOut::new(Amount::from_sat(0), script_pubkey.to_string())],
+            lock_time: 0,
+        };
+        let tiny = transaction("6a");
+        assert_eq!(tiny.base_size(), 61);
+        assert!(!tiny.is_relayable_size());
+        assert!(!transaction("6a02aabb").is_relayable_size());
+        assert!(transaction("6a03aabbcc").is_relayable_size());
+
+        let p2pkh = transaction("76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac");
+        assert!(p2pkh.is_relayable_size());
+    }
+
+    #[test]
+    fn test_script_eq_ignores_case() {
+        let txout = TxOut::new(
+            Amount::from_sat(1_028_587),
+            "76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac".to_string(),
+        );
+        assert!(txout.script_eq("76A9144EF88A0B04E3AD6D1888DA4BE260D6735E0D308488AC"));
+        assert!(!txout.script_eq("76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488"));
+
+        let transaction = Transaction {
+            version: Version(1),
+            inputs: vec![],
+            outputs: vec![TxOut::new(Amount::from_sat(1), "6a".to_string()), txout],
+            lock_time: 0,
+        };
+        let (index, output) = transaction.find_output_to("76A9144EF88A0B04E3AD6D1888DA4BE260D6735E0D308488AC").unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(output.amount, Amount::from_sat(1_028_587));
+        assert!(transaction.pays_to_script("6A"));
+        assert!(!transaction.pays_to_script("6a00"));
+    }
+
+    #[test]
+    fn test_dust_p2wpkh() {
+        let txout = TxOut {
+            amount: Amount::from_sat(1),
+            script_pubkey: "00146f048d1381aa546a3e89e87f7549efc45f150b7f".to_string(),
+        };
+        assert_eq!(txout.dust_threshold(DEFAULT_DUST_RELAY_FEE), Some(294));
+        assert!(txout.is_dust(DEFAULT_DUST_RELAY_FEE));
+    }
+
+    #[test]
+    fn test_dust_invalid_hex() {
+        let txout = TxOut { amount: Amount::from_sat(1), script_pubkey: "0014zz".to_string() };
+        assert_eq!(txout.dust_threshold(DEFAULT_DUST_RELAY_FEE), None);
+        assert!(!txout.is_dust(DEFAULT_DUST_RELAY_FEE));
+        assert!(serde_json::to_string(&txout).unwrap().contains("\"dust\":false"));
+    }
+
+    #[test]
+    fn test_not_dust() {
+        let txout = TxOut {
+            amount: Amount::from_sat(1_028_587),
+            script_pubkey: "76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac".to_string(),
+        };
+        assert_eq!(txout.dust_threshold(DEFAULT_DUST_RELAY_FEE), Some(546));
+        assert!(!txout.is_dust(DEFAULT_DUST_RELAY_FEE));
+    }
+
+    #[test]
+    fn test_valid_amount() {
+        let txout = TxOut {
+            amount: Amount::from_sat(MAX_MONEY),
+            script_pubkey: "0014d850c02b89821f0f189ca7e81756c102241f7f40".to_string(),
+        };
+        assert!(txout.is_valid_amount());
+
+        let txout = TxOut { amount: Amount::from_sat(MAX_MONEY + 1), ..txout };
+        assert!(!txout.is_valid_amount());
+    }
+
+    #[test]
+    fn test_p2sh_multisig_redeem_script() {
+        // OP_0 <sig> <sig> <2-of-3 multisig redeem script>
+        let redeem_script = "52210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef92821030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd52103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abf53ae";
+        let txin = TxIn {
+            previous_output: OutPoint { txid: Txid([1; 32]), vout: 0 },
+            script_sig: format!(
+                "00{sig}{sig}4c69{redeem_script}",
+                sig = "473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101",
+            ),
+            sequence: 0xffffffff,
+            witness: Witness::new(),
+        };
+        assert_eq!(hex::encode(txin.redeem_script().unwrap()), redeem_script);
+
+        // the dummy OP_0 and the redeem script are skipped
+        let signatures = txin.multisig_signatures().unwrap();
+        assert_eq!(signatures.len(), 2);
+        assert!(signatures.iter().all(|signature| signature.len() == 71 && signature[0] == 0x30));
+
+        let json = serde_json::to_value(&txin).unwrap();
+        assert_eq!(
+            json["redeem_script"],
+            "2 0355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928 \
+             030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5 \
+             03c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abf 3 OP_CHECKMULTISIG"
+        );
+    }
+
+    #[test]
+    fn test_p2pkh_has_no_redeem_script() {
+        let txin = TxIn {
+            previous_output: OutPoint { txid: Txid([1; 32]), vout: 0 },
+            script_sig: "4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5".to_string(),
+            sequence: 0xffffffff,
+            witness: Witness::new(),
+        };
+        assert_eq!(txin.redeem_script(), None);
+        assert_eq!(txin.multisig_signatures(), None);
+    }
+
+    #[test]
+    fn test_version_as_i32() {
+        assert_eq!(Version(0xffffffff).as_i32(), -1);
+        assert_eq!(Version(0xffffffff).warning().unwrap(), "version -1 is negative");
+        assert_eq!(Version(2).as_i32(), 2);
+        assert_eq!(Version(2).warning(), None);
+        assert_eq!(Version(4).warning().unwrap(), "version 4 is above the standard maximum of 3");
+    }
+
+    #[test]
+    fn test_encode_txout_slice() {
+        let outputs = vec![
+            TxOut { amount: Amount::from_sat(1_028_587), script_pubkey: "76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac".to_string() },
+            TxOut { amount: Amount::from_sat(2_002_000), script_pubkey: "a91476c0c8f2fc403c5edaea365f6a284317b9cdf72587".to_string() },
+        ];
+        let mut from_vec = Vec::new();
+        let vec_len = outputs.consensus_encode(&mut from_vec).unwrap();
+
+        let slice: &[TxOut] = &outputs;
+        let mut from_slice = Vec::new();
+        let slice_len = slice.consensus_encode(&mut from_slice).unwrap();
+
+        assert_eq!(from_slice, from_vec);
+        assert_eq!(slice_len, vec_len);
+        assert_eq!(hex::encode(from_slice), "02ebb10f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac508c1e000000000017a91476c0c8f2fc403c5edaea365f6a284317b9cdf72587");
+
+        // a sub-slice only encodes the elements it borrows
+        let mut first_only = Vec::new();
+        outputs[..1].consensus_encode(&mut first_only).unwrap();
+        assert_eq!(first_only[0], 1);
+        assert_eq!(first_only.len(), 1 + outputs[0].serialized_len());
+    }
+
+    #[test]
+    fn test_witness_from_items_round_trip() {
+        let witness = Witness::from_items(vec![vec![0x30, 0x44, 0x01], vec![0x02; 33]]);
+        let mut bytes = Vec::new();
+        let len = witness.consensus_encode(&mut bytes).unwrap();
+        assert_eq!(len, witness.serialized_len());
+        assert_eq!(bytes[..5], [0x02, 0x03, 0x30, 0x44, 0x01]);
+
+        let decoded = Witness::consensus_decode(&mut bytes.as_slice()).unwrap();
+        assert_eq!(decoded, witness);
+        assert_eq!(
+            serde_json::to_string(&decoded).unwrap(),
+            format!(r#"["304401","{}"]"#, "02".repeat(33))
+        );
+    }
+
+    #[test]
+    fn test_witness_empty_item() {
+        let witness = Witness { content: vec![vec![], vec![0xab, 0xcd], vec![]] };
+        let json = serde_json::to_string(&witness).unwrap();
+        assert_eq!(json, r#"["","abcd",""]"#);
+    }
+
+    #[test]
+    fn test_decode_empty_witness_vs_single_empty_item() {
+        let empty = Witness::consensus_decode(&mut [0x00_u8].as_slice()).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.serialized_len(), 1);
+
+        let single_empty_item = Witness::consensus_decode(&mut [0x01_u8, 0x00].as_slice()).unwrap();
+        assert!(!single_empty_item.is_empty());
+        assert_eq!(single_empty_item.content, vec![Vec::<u8>::new()]);
+        assert_eq!(single_empty_item.serialized_len(), 2);
+        assert_eq!(serde_json::to_string(&single_empty_item).unwrap(), r#"[""]"#);
+    }
+
+    #[test]
+    fn test_p2wsh_witness_script() {
+        let multisig = hex::decode("52210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef92821030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd52103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abf53ae").unwrap();
+        let txin = TxIn {
+            previous_output: OutPoint { txid: Txid([0; 32]), vout: 0 },
+            script_sig: String::new(),
+            sequence: 0xffffffff,
+            witness: Witness {
+                content: vec![
+                    vec![],
+                    hex::decode("3044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101").unwrap(),
+                    hex::decode("3045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c4701").unwrap(),
+                    multisig.clone(),
+                ],
+            },
+        };
+        assert_eq!(txin.witness_script(), Some(multisig.as_slice()));
+
+        let asm = Script::from_bytes(multisig).asm();
+        assert_eq!(asm, "2 0355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928 030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5 03c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abf 3 OP_CHECKMULTISIG");
+    }
+
+    #[test]
+    fn test_p2wpkh_has_no_witness_script() {
+        let txin = TxIn {
+            previous_output: OutPoint { txid: Txid([0; 32]), vout: 0 },
+            script_sig: String::new(),
+            sequence: 0xffffffff,
+            witness: Witness {
+                content: vec![
+                    hex::decode("3044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101").unwrap(),
+                    hex::decode("0355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928").unwrap(),
+                ],
+            },
+        };
+        assert_eq!(txin.witness_script(), None);
+    }
+
+    #[test]
+    fn test_taproot_has_no_witness_script() {
+        // a signature, the tapscript `<key> OP_CHECKSIG` and a control block for a leaf at the root
+        let mut tapscript = vec![0x20];
+        tapscript.extend([0x79; 32]);
+        tapscript.push(0xac);
+        let mut control_block = vec![0xc0];
+        control_block.extend([0x79; 32]);
+        let txin = TxIn {
+            previous_output: OutPoint { txid: Txid([0; 32]), vout: 0 },
+            script_sig: String::new(),
+            sequence: 0xffffffff,
+            witness: Witness { content: vec![vec![0xab; 64], tapscript, control_block] },
+        };
+        assert_eq!(txin.spend_type(), SpendType::TaprootScriptPath);
+        assert_eq!(txin.witness_script(), None);
+        assert!(serde_json::to_value(&txin).unwrap().get("witness_script").is_none());
+
+        // a key path spend with an annex
+        let txin = TxIn { witness: Witness { content: vec![vec![0xab; 64], vec![0x50, 0x01]] }, ..txin };
+        assert_eq!(txin.spend_type(), SpendType::TaprootKeyPath);
+        assert_eq!(txin.witness_script(), None);
+    }
+
+    #[test]
+    fn test_witness_annex() {
+        let witness = Witness { content: vec![vec![0xab; 64], vec![0x50, 0x01, 0x02]] };
+        assert_eq!(witness.annex(), Some([0x50, 0x01, 0x02].as_slice()));
+        assert_eq!(witness.stack(), [vec![0xab; 64]].as_slice());
+        let json = serde_json::to_string(&witness).unwrap();
+        assert_eq!(json, format!(r#"["{}"]"#, "ab".repeat(64)));
+    }
+
+    #[test]
+    fn test_witness_without_annex() {
+        // a single item starting with 0x50 is not an annex
+        let witness = Witness { content: vec![vec![0x50, 0x01]] };
+        assert_eq!(witness.annex(), None);
+        assert_eq!(witness.stack().len(), 1);
+
+        let witness = Witness { content: vec![vec![0xab; 64], vec![0x51, 0x01]] };
+        assert_eq!(witness.annex(), None);
+        assert_eq!(witness.stack().len(), 2);
+    }
+
+    #[test]
+    fn test_encode_invalid_script_hex() {
+        let outputs = vec![TxOut {
+            amount: Amount::from_sat(1000),
+            script_pubkey: "zz".to_string(),
+        }];
+        let mut bytes = Vec::new();
+        let result = outputs.consensus_encode(&mut bytes);
+        assert!(matches!(result, Err(Error::ParseFailed("invalid script hex"))));
+    }
+
+    #[test]
+    fn test_value_btc() {
+        let txout = TxOut {
+            amount: Amount::from_sat(100_000_000),
+            script_pubkey: "00146f048d1381aa546a3e89e87f7549efc45f150b7f".to_string(),
+        };
+        assert_eq!(txout.value_btc(), 1.0);
+
+        let txout = TxOut {
+            amount: Amount::from_sat(50_000_000),
+            script_pubkey: "00146f048d1381aa546a3e89e87f7549efc45f150b7f".to_string(),
+        };
+        assert_eq!(txout.value_btc(), 0.5);
+        assert_eq!(txout.amount.btc(), 0.5);
+    }
+
+    #[test]
+    fn test_amount_delta() {
+        let original_fee = Amount::from_sat(1_000);
+        let replacement_fee = Amount::from_sat(2_500);
+        assert_eq!(AmountDelta::from_diff(replacement_fee, original_fee), Some(AmountDelta(1_500)));
+        assert_eq!(AmountDelta::from_diff(original_fee, replacement_fee), Some(AmountDelta(-1_500)));
+        assert_eq!(AmountDelta::from_diff(Amount::from_sat(u64::MAX), original_fee), None);
+        assert_eq!(AmountDelta::from_diff(Amount::from_sat(i64::MAX as u64), Amount::from_sat(0)), Some(AmountDelta(i64::MAX)));
+    }
+
+    #[test]
+    fn test_op_return_payloads() {
+        let transaction = Transaction {
+            version: Version(2),
+            inputs: vec![],
+            outputs: vec![
+                TxOut { amount: Amount::from_sat(0), script_pubkey: "6a0568656c6c6f".to_string() },
+                TxOut {
+                    amount: Amount::from_sat(10_000),
+                    script_pubkey: "00146f048d1381aa546a3e89e87f7549efc45f150b7f".to_string(),
+                },
+                // two pushes, the second one with OP_PUSHDATA1
+                TxOut { amount: Amount::from_sat(0), script_pubkey: "6a02abcd4c03010203".to_string() },
+            ],
+            lock_time: 0,
+        };
+        assert!(transaction.contains_op_return());
+        assert_eq!(
+            transaction.op_return_payloads(),
+            vec![b"hello".to_vec(), vec![0xab, 0xcd, 0x01, 0x02, 0x03]]
+        );
+
+        // an OP_RETURN followed by a non-push opcode carries no data we can extract
+        let txout = TxOut { amount: Amount::from_sat(0), script_pubkey: "6a0568656c6c6f75".to_string() };
+        assert_eq!(txout.op_return_data(), None);
+    }
+
+    #[test]
+    fn test_legacy_serialization() {
+        let transaction = Transaction::consensus_decode(&mut hex::decode(SEGWIT_TRANSACTION_HEX).unwrap().as_slice()).unwrap();
+
+        let legacy = transaction.legacy_serialization();
+        assert_eq!(crate::hashes::sha256d(&legacy), transaction.txid().0);
+        assert_eq!(legacy.len(), transaction.base_size());
+        // the marker, flag and witness are left out
+        assert_eq!(hex::encode(&legacy[..5]), "0200000001");
+        assert_eq!(hex::encode(&legacy[legacy.len() - 4..]), "bff62400");
+    }
+
+    #[test]
+    fn test_absolute_timelock() {
+        // 500000 OP_CHECKLOCKTIMEVERIFY OP_DROP followed by a P2PKH script
+        let txout = TxOut::new(Amount::from_sat(1_000), "0320a107b17576a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac".to_string());
+        assert_eq!(txout.absolute_timelock(), Some(LockTime::Height(500_000)));
+
+        // the same with 1700000000, 2023-11-14 22:13:20 UTC
+        let txout = TxOut::new(Amount::from_sat(1_000), "0400f15365b17576a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac".to_string());
+        assert_eq!(txout.absolute_timelock(), Some(LockTime::Time(1_700_000_000)));
+
+        // -1 OP_CHECKLOCKTIMEVERIFY OP_DROP can't be satisfied
+        let txout = TxOut::new(Amount::from_sat(1_000), "4fb17551".to_string());
+        assert_eq!(txout.absolute_timelock(), None);
+
+        // a relative timelock, and plain P2PKH
+        let txout = TxOut::new(Amount::from_sat(1_000), "029000b275210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928ac".to_string());
+        assert_eq!(txout.absolute_timelock(), None);
+        let txout = TxOut::new(Amount::from_sat(1_000), "76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac".to_string());
+        assert_eq!(txout.absolute_timelock(), None);
+    }
+
+    #[test]
+    fn test_txid_byte_order() {
+        // the segwit test transaction's first input spends this txid
+        let display = "0c0fe4cc11c477231ad80de3496e20f40cc3088797c50aec8996e955c87e46d2";
+        let internal = "d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c";
+
+        let txid = Txid::from_display_hex(display).unwrap();
+        assert_eq!(hex::encode(txid.to_display_bytes()), display);
+        assert_eq!(hex::encode(txid.to_internal_bytes()), internal);
+        assert_eq!(Txid::from_internal_hex(internal).unwrap(), txid);
+
+        let mut reversed = txid.to_internal_bytes();
+        reversed.reverse();
+        assert_eq!(reversed, txid.to_display_bytes());
+        assert_eq!(txid.to_string(), display);
+    }
+
+    #[test]
+    fn test_amount_to_btc_string() {
+        assert_eq!(Amount::from_sat(1_500_000).to_btc_string(), "0.01500000");
+        assert_eq!(Amount::from_sat(1_500_000).to_btc_string_trimmed(), "0.015");
+        assert_eq!(Amount::from_sat(1).to_btc_string_trimmed(), "0.00000001");
+        assert_eq!(Amount::from_sat(100_000_000).to_btc_string_trimmed(), "1");
+        assert_eq!(Amount::from_sat(0).to_btc_string_trimmed(), "0");
+        assert_eq!(Amount::from_sat(MAX_MONEY).to_btc_string(), "21000000.00000000");
+    }
+
+    #[test]
+    fn test_txin_txout_new() {
+        let outpoint = OutPoint { txid: Txid([0x11; 32]), vout: 1 };
+        let txin = TxIn::new(outpoint, String::new());
+        assert_eq!(txin.sequence, 0xffffffff);
+        assert!(txin.witness.items().is_empty());
+        let mut bytes = vec![];
+        txin.consensus_encode(&mut bytes).unwrap();
+        assert_eq!(hex::encode(bytes), format!("{}0100000000ffffffff", "11".repeat(32)));
+
+        let txout = TxOut::new(
+            Amount::from_sat(1_000),
+            "00146f048d1381aa546a3e89e87f7549efc45f150b7f".to_string(),
+        );
+        let mut bytes = vec![];
+        txout.consensus_encode(&mut bytes).unwrap();
+        assert_eq!(hex::encode(bytes), "e8030000000000001600146f048d1381aa546a3e89e87f7549efc45f150b7f");
+    }
+
+    #[test]
+    fn test_amount_checked_sub() {
+        let a = Amount::from_sat(2_500);
+        let b = Amount::from_sat(1_000);
+        assert_eq!(a.checked_sub(b), Some(Amount::from_sat(1_500)));
+        assert_eq!(b.checked_sub(a), None);
+    }
+
+    #[test]
+    fn test_outpoint_display_from_str() {
+        let s = "8073cdf947ac97c23b77b055217da78d3ad71d30e1f6c095be8b30f7d6c1d542:1";
+        let outpoint: OutPoint = s.parse().unwrap();
+        assert_eq!(outpoint.vout, 1);
+        assert_eq!(outpoint.txid.0[0], 0x42);
+        assert_eq!(outpoint.to_string(), s);
+
+        assert!("8073cdf947ac97c23b77b055217da78d3ad71d30e1f6c095be8b30f7d6c1d542".parse::<OutPoint>().is_err());
+        assert!("8073cdf9:1".parse::<OutPoint>().is_err());
+        assert!("8073cdf947ac97c23b77b055217da78d3ad71d30e1f6c095be8b30f7d6c1d542:x".parse::<OutPoint>().is_err());
+    }
+
+    #[test]
+    fn test_outpoint_decode() {
+        // the 36 bytes at the start of an input: txid followed by vout
+        let bytes = hex::decode("42d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd738001000000").unwrap();
+        let outpoint = OutPoint::consensus_decode(&mut bytes.as_slice()).unwrap();
+
+        let mut old_layout = bytes.as_slice();
+        let txid = Txid::consensus_decode(&mut old_layout).unwrap();
+        let vout = u32::consensus_decode(&mut old_layout).unwrap();
+        assert_eq!(outpoint, OutPoint { txid, vout });
+
+        let mut encoded = Vec::new();
+        outpoint.consensus_encode(&mut encoded).unwrap();
+        assert_eq!(encoded, bytes);
+    }
+
+    #[test]
+    fn test_compact_size_boundaries() {
+        let cases: [(u64, &str); 8] = [
+            (0xFC, "fc"),
+            (0xFD, "fdfd00"),
+            (0xFFFF, "fdffff"),
+            (0x10000, "fe00000100"),
+            (0xFFFFFFFF, "feffffffff"),
+            (0x100000000, "ff0000000001000000"),
+            (u64::MAX, "ffffffffffffffffff"),
+            (0, "00"),
+        ];
+        for (n, expected) in cases {
+            let mut bytes = Vec::new();
+            let len = CompactSize(n).consensus_encode(&mut bytes).unwrap();
+            assert_eq!(hex::encode(&bytes), expected);
+            assert_eq!(len, bytes.len());
+            assert_eq!(len, CompactSize(n).serialized_len());
+
+            let decoded = CompactSize::consensus_decode(&mut bytes.as_slice()).unwrap();
+            assert_eq!(decoded.0, n);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+use crate::transaction::{Transaction, TxIn, TxOut};
+
+// A plain representation of a transaction for presenting it, kept apart from the
+// types that know how to encode and decode the wire format. Everything is stored
+// as simple values so it can be deserialized again without any of the codec.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionView {
+    pub txid: String,
+    pub wtxid: String,
+    pub version: u32,
+    pub size: usize,
+    pub vsize: usize,
+    pub weight: usize,
+    pub inputs: Vec<TxInView>,
+    pub outputs: Vec<TxOutView>,
+    pub locktime: u32,
+    pub flags: TransactionFlags,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxInView {
+    pub txid: String,
+    pub vout: u32,
+    pub script_sig: String,
+    pub witness: Vec<String>,
+    pub sequence: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TxOutView {
+    pub value_sat: u64,
+    pub script_pubkey: String,
+}
+
+// Properties computed from the transaction rather than read from it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionFlags {
+    pub segwit: bool,
+    pub coinbase: bool,
+    pub bip69: bool,
+}
+
+impl Transaction {
+    pub fn to_view(&self) -> TransactionView {
+        TransactionView {
+            txid: self.txid().to_string(),
+            wtxid: self.wtxid().to_string(),
+            version: self.version.0,
+            size: self.total_size(),
+            vsize: self.vsize(),
+            weight: self.weight(),
+            inputs: self.inputs.iter().map(TxInView::from).collect(),
+            outputs: self.outputs.iter().map(TxOutView::from).collect(),
+            locktime: self.lock_time,
+            flags: TransactionFlags {
+                segwit: self.is_segwit(),
+                coinbase: self.is_coinbase(),
+                bip69: self.is_bip69_sorted(),
+            },
+        }
+    }
+}
+
+impl From<&TxIn> for TxInView {
+    fn from(input: &TxIn) -> Self {
+        TxInView {
+            txid: input.previous_output.txid.to_string(),
+            vout: input.previous_output.vout,
+            script_sig: input.script_sig.clone(),
+            witness: input.witness.items().iter().map(hex::encode).collect(),
+            sequence: input.sequence,
+        }
+    }
+}
+
+impl From<&TxOut> for TxOutView {
+    fn from(output: &TxOut) -> Self {
+        TxOutView {
+            value_sat: output.amount.to_sat(),
+            script_pubkey: output.script_pubkey.clone(),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+// Helpers shared by the integration tests and the decode benchmark
+
+// Builds a version 1 transaction spending `inputs` made up outpoints with empty
+// scriptSigs into a single output, the same shape as the real 20,000 input
+// transaction 52539a56b1eb890504b775171923430f0355eb836a57134ba598170a2f8980c1
+pub fn large_transaction(inputs: u16) -> Vec<u8> {
+    let mut bytes = vec![0x01, 0x00, 0x00, 0x00];
+    bytes.push(0xfd);
+    bytes.extend(inputs.to_le_bytes());
+    for i in 0..inputs {
+        bytes.extend([0xab; 28]);
+        bytes.extend((i as u32).to_le_bytes());
+        bytes.extend(0_u32.to_le_bytes());
+        bytes.push(0x00);
+        bytes.extend(0xffffffff_u32.to_le_bytes());
+    }
+    bytes.push(0x01);
+    bytes.extend(100_000_u64.to_le_bytes());
+    bytes.push(0x16);
+    bytes.extend(hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap());
+    bytes.extend(0_u32.to_le_bytes());
+    bytes
+}
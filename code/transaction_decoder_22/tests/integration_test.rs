@@ -1,17 +1,980 @@
+use transaction_decoder_22::{Amount, AmountDelta, AmountUnit, FeeBucket, FieldDiff, KeyStyle, Network, OutPoint, Options, Transaction, TransactionFlags, TransactionView, Txid};
+use transaction_decoder_22::script::ScriptType;
+use transaction_decoder_22::transaction::{CompactSize, SpendType, Warning, Witness, DEFAULT_DUST_RELAY_FEE};
 use std::fs;
+use std::time::{Duration, Instant};
+
+mod common;
+
+const LEGACY_TRANSACTION_HEX: &str = "010000000242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffff75c87cc5f3150eefc1c04c0246e7e0b370e64b17d6226c44b333a6f4ca14b49c000000006b483045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c47012103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abffeffffff02ebb10f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac508c1e000000000017a91476c0c8f2fc403c5edaea365f6a284317b9cdf7258700000000";
+const SEGWIT_TRANSACTION_HEX: &str = "02000000000101d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff028f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7fa9ce0f0000000000160014d850c02b89821f0f189ca7e81756c102241f7f4002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928bff62400";
 
 #[test]
 fn test_legacy() {
-    let raw_transaction_hex = "010000000242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffff75c87cc5f3150eefc1c04c0246e7e0b370e64b17d6226c44b333a6f4ca14b49c000000006b483045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c47012103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abffeffffff02ebb10f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac508c1e000000000017a91476c0c8f2fc403c5edaea365f6a284317b9cdf7258700000000";
-    let json = transaction_decoder_22::run(raw_transaction_hex.to_string()).unwrap();
+    let json = transaction_decoder_22::run(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
     let expected = fs::read_to_string("tests/test_transaction_legacy.json").unwrap();
     assert_eq!(expected, json);
 }
 
 #[test]
 fn test_segwit() {
-    let raw_transaction_hex = "02000000000101d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff028f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7fa9ce0f0000000000160014d850c02b89821f0f189ca7e81756c102241f7f4002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928bff62400";
-    let json = transaction_decoder_22::run(raw_transaction_hex.to_string()).unwrap();
+    let json = transaction_decoder_22::run(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
     let expected = fs::read_to_string("tests/test_transaction_segwit.json").unwrap();
     assert_eq!(expected, json);
 }
+
+#[test]
+fn test_bip69() {
+    let legacy = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(legacy.is_bip69_sorted());
+
+    // the larger output comes first
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(!segwit.is_bip69_sorted());
+}
+
+#[test]
+fn test_p2wpkh_vsize() {
+    // matches the size, vsize and weight reported by Bitcoin Core
+    let transaction = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(transaction.base_size(), 113);
+    assert_eq!(transaction.total_size(), 222);
+    assert_eq!(transaction.weight(), 561);
+    assert_eq!(transaction.vsize(), 141);
+}
+
+#[test]
+fn test_feerate() {
+    let transaction = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    // the outputs add up to 3,070,520 sats, so spending 3,071,930 sats pays a
+    // 1,410 sat fee, which is 10 sat/vB at 141 vbytes
+    let prevouts = [Amount::from_sat(3_071_930)];
+    assert_eq!(transaction.fee(&prevouts).unwrap(), Amount::from_sat(1_410));
+    assert_eq!(transaction.feerate(&prevouts).unwrap(), 10.0);
+
+    let err = transaction.feerate(&[]).unwrap_err();
+    assert_eq!(err.to_string(), "invalid fee: expected one prevout amount per input");
+
+    let err = transaction.feerate(&[Amount::from_sat(3_000_000)]).unwrap_err();
+    assert_eq!(err.to_string(), "invalid fee: outputs are worth more than the inputs");
+}
+
+#[test]
+fn test_feerate_bucket() {
+    // 141 vbytes, so each sat/vB costs 141 sats on top of the 3,070,520 sats of outputs
+    let transaction = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    let bucket = |sat_per_vbyte: u64| {
+        transaction.feerate_bucket(&[Amount::from_sat(3_070_520 + 141 * sat_per_vbyte)]).unwrap()
+    };
+    assert_eq!(bucket(0), FeeBucket::VeryLow);
+    assert_eq!(bucket(1), FeeBucket::VeryLow);
+    assert_eq!(bucket(2), FeeBucket::Low);
+    assert_eq!(bucket(4), FeeBucket::Low);
+    assert_eq!(bucket(5), FeeBucket::Medium);
+    assert_eq!(bucket(10), FeeBucket::Medium);
+    assert_eq!(bucket(20), FeeBucket::High);
+    assert_eq!(bucket(49), FeeBucket::High);
+    assert_eq!(bucket(50), FeeBucket::VeryHigh);
+    assert_eq!(bucket(500), FeeBucket::VeryHigh);
+
+    // fractional rates just under a threshold stay in the lower bucket
+    assert_eq!(FeeBucket::from_feerate(1.99), FeeBucket::VeryLow);
+    assert_eq!(FeeBucket::from_feerate(19.9), FeeBucket::Medium);
+    assert!(transaction.feerate_bucket(&[]).is_err());
+}
+
+#[test]
+fn test_spent_and_created_outputs_totals() {
+    let transaction = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    // made up amounts for the two outputs being spent
+    let prevouts = [Amount::from_sat(1_500_000), Amount::from_sat(1_540_587)];
+    let spent = transaction.spent_outputs_total(&prevouts).unwrap();
+    let created = transaction.created_outputs_total().unwrap();
+    assert_eq!(spent, Amount::from_sat(3_040_587));
+    assert_eq!(created, Amount::from_sat(1_028_587 + 2_002_000));
+    assert_eq!(spent.checked_sub(created), Some(Amount::from_sat(10_000)));
+    assert_eq!(transaction.fee(&prevouts).unwrap(), Amount::from_sat(10_000));
+
+    let err = transaction.spent_outputs_total(&prevouts[..1]).unwrap_err();
+    assert_eq!(err.to_string(), "invalid fee: expected one prevout amount per input");
+
+    // totals past u64::MAX are errors rather than wrapping around
+    let err = transaction.spent_outputs_total(&[Amount::from_sat(u64::MAX), Amount::from_sat(1)]).unwrap_err();
+    assert_eq!(err.to_string(), "prevout amounts add up to more than 18446744073709551615 satoshis");
+    let mut overflowing = transaction.clone();
+    overflowing.outputs[0].amount = Amount::from_sat(u64::MAX);
+    let err = overflowing.created_outputs_total().unwrap_err();
+    assert_eq!(err.to_string(), "output amounts add up to more than 18446744073709551615 satoshis");
+    assert!(overflowing.fee(&prevouts).is_err());
+}
+
+#[test]
+fn test_to_view() {
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    let view = segwit.to_view();
+    assert_eq!(view.txid, "17e1fcaae34575d0d1566c1ae64bf4c9f7b7b9df0ff505015d3eb72460fe3a61");
+    assert_ne!(view.wtxid, view.txid);
+    assert_eq!(view.version, 2);
+    assert_eq!((view.size, view.vsize, view.weight), (222, 141, 561));
+    assert_eq!(view.inputs[0].witness.len(), 2);
+    assert_eq!(view.outputs[0].value_sat, 2_034_575);
+    assert_eq!(view.locktime, 2_422_463);
+    assert_eq!(view.flags, TransactionFlags { segwit: true, coinbase: false, bip69: false });
+
+    // the view can be read back from its JSON
+    let json = serde_json::to_string(&view).unwrap();
+    let deserialized: TransactionView = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, view);
+
+    // without witnesses the wtxid is the txid
+    let legacy = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    let view = legacy.to_view();
+    assert_eq!(view.wtxid, view.txid);
+    assert!(view.inputs[0].witness.is_empty());
+    assert_eq!(view.flags, TransactionFlags { segwit: false, coinbase: false, bip69: true });
+}
+
+#[test]
+fn test_conflicts() {
+    let legacy = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(!transaction_decoder_22::conflicts(&legacy, &segwit));
+
+    // a transaction spending the legacy transaction's second input and the segwit one's input
+    let mut both = segwit.clone();
+    both.inputs.insert(0, legacy.inputs[1].clone());
+    assert!(transaction_decoder_22::conflicts(&legacy, &both));
+    assert!(transaction_decoder_22::conflicts(&both, &segwit));
+    assert!(transaction_decoder_22::conflicts(&legacy, &legacy));
+}
+
+#[test]
+fn test_diff() {
+    let original = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(original.diff(&original).is_empty());
+
+    // a replacement that pays a higher fee out of the first output
+    let mut replacement = original.clone();
+    replacement.outputs[0].amount = Amount::from_sat(2_034_575 - 1_000);
+    assert_eq!(
+        original.diff(&replacement),
+        vec![
+            FieldDiff::OutputAmount {
+                index: 0,
+                old: Amount::from_sat(2_034_575),
+                new: Amount::from_sat(2_033_575),
+            },
+            FieldDiff::Fee(AmountDelta(1_000)),
+        ]
+    );
+}
+
+#[test]
+fn test_locktime_is_enforced() {
+    // a non-zero locktime with a non-final sequence
+    let mut transaction = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(transaction.lock_time, 2_422_463);
+    assert_eq!(transaction.inputs[0].sequence, 0xfffffffe);
+    assert!(transaction.locktime_is_enforced());
+
+    // the same locktime is ignored once every sequence is final
+    transaction.inputs[0].sequence = 0xffffffff;
+    assert!(!transaction.locktime_is_enforced());
+    let value: serde_json::Value = serde_json::to_value(&transaction).unwrap();
+    assert_eq!(value["locktime"], 2_422_463);
+    assert_eq!(value["locktime_enforced"], false);
+
+    // a locktime of 0 is never enforced
+    let legacy = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(legacy.lock_time, 0);
+    assert!(!legacy.locktime_is_enforced());
+}
+
+#[test]
+fn test_decode_with_spans() {
+    let legacy_bytes = hex::decode(LEGACY_TRANSACTION_HEX).unwrap();
+    let (legacy, spans) = Transaction::decode_with_spans(&legacy_bytes).unwrap();
+    assert_eq!(legacy.txid(), transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap().txid());
+    assert_eq!(spans.version, 0..4);
+    assert_eq!(spans.marker_and_flag, None);
+    assert_eq!(spans.input_count, 4..5);
+    assert_eq!(spans.inputs, vec![5..152, 152..300]);
+    assert!(spans.witnesses.is_empty());
+    assert_eq!(spans.locktime.end, legacy_bytes.len());
+
+    let segwit_bytes = hex::decode(SEGWIT_TRANSACTION_HEX).unwrap();
+    let (segwit, spans) = Transaction::decode_with_spans(&segwit_bytes).unwrap();
+    assert_eq!(segwit.wtxid(), transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap().wtxid());
+    assert_eq!(spans.version, 0..4);
+    assert_eq!(spans.marker_and_flag, Some(4..6));
+    assert_eq!(spans.input_count, 6..7);
+    assert_eq!(spans.outputs.len(), 2);
+    assert_eq!(spans.witnesses.len(), 1);
+    assert_eq!(spans.witnesses[0].end, spans.locktime.start);
+    assert_eq!(spans.locktime, segwit_bytes.len() - 4..segwit_bytes.len());
+}
+
+#[test]
+fn test_has_valid_amounts() {
+    let mut transaction = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(transaction.has_valid_amounts());
+
+    // each output is just under the cap, but together they're over it
+    transaction.outputs[0].amount = Amount::from_sat(2_099_999_999_999_999);
+    assert!(transaction.outputs[0].is_valid_amount());
+    assert!(transaction.outputs[1].is_valid_amount());
+    assert!(!transaction.has_valid_amounts());
+
+    assert_eq!(transaction.warnings(), [Warning::AmountsExceedSupply]);
+    let value: serde_json::Value = serde_json::to_value(&transaction).unwrap();
+    assert_eq!(value["warnings"], serde_json::json!(["output amounts exceed the 21 million BTC supply"]));
+
+    transaction.outputs[1].amount = Amount::from_sat(1);
+    assert!(transaction.has_valid_amounts());
+    let value: serde_json::Value = serde_json::to_value(&transaction).unwrap();
+    assert!(value.get("warnings").is_none());
+}
+
+#[test]
+fn test_include_hex() {
+    let options = Options {
+        include_hex: true,
+        ..Default::default()
+    };
+    let json = transaction_decoder_22::run_with_options(SEGWIT_TRANSACTION_HEX.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["hex"], SEGWIT_TRANSACTION_HEX);
+
+    let json = transaction_decoder_22::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["hex"], LEGACY_TRANSACTION_HEX);
+}
+
+#[test]
+fn test_verbose_witness() {
+    let options = Options {
+        verbose_witness: true,
+        ..Default::default()
+    };
+    let json = transaction_decoder_22::run_with_options(SEGWIT_TRANSACTION_HEX.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        value["witnesses"],
+        serde_json::json!([{
+            "items": [
+                {
+                    "hex": "3044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101",
+                    "size": 71
+                },
+                {
+                    "hex": "0355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928",
+                    "size": 33
+                }
+            ],
+            "witness_bytes": 107
+        }])
+    );
+    // the witness inside each input keeps its flat array
+    assert_eq!(value["inputs"][0]["txinwitness"].as_array().unwrap().len(), 2);
+
+    let json = transaction_decoder_22::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert!(value.get("witnesses").is_none());
+}
+
+#[test]
+fn test_trim_amounts() {
+    // a made up input and two P2WPKH outputs of 1 and 1,500,000 satoshis
+    let raw_transaction_hex = format!(
+        "0100000001{}ffffffff00ffffffff02{}160014{}{}160014{}00000000",
+        "11".repeat(32), "0100000000000000", "22".repeat(20), "60e3160000000000", "33".repeat(20)
+    );
+    let json = transaction_decoder_22::run(raw_transaction_hex.clone()).unwrap();
+    assert!(json.contains("\"amount\": 1e-8,"));
+    assert!(json.contains("\"amount\": 0.015,"));
+
+    let options = Options {
+        trim_amounts: true,
+        ..Default::default()
+    };
+    let trimmed = transaction_decoder_22::run_with_options(raw_transaction_hex, &options).unwrap();
+    assert!(trimmed.contains("\"amount\": 0.00000001,"));
+    assert!(trimmed.contains("\"amount\": 0.015,"));
+    // only the amounts change
+    assert_eq!(trimmed.replace("0.00000001", "1e-8"), json);
+}
+
+#[test]
+fn test_scripts() {
+    let options = Options {
+        scripts: true,
+        ..Default::default()
+    };
+    let json = transaction_decoder_22::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        value["outputs"][0]["script_pubkey"],
+        serde_json::json!({
+            "hex": "76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac",
+            "asm": "OP_DUP OP_HASH160 4ef88a0b04e3ad6d1888da4be260d6735e0d3084 OP_EQUALVERIFY OP_CHECKSIG",
+            "push_count": 1
+        })
+    );
+    assert_eq!(
+        value["inputs"][0]["scriptSig"]["asm"],
+        "30440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b01 030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5"
+    );
+    assert_eq!(value["inputs"][0]["scriptSig"]["push_count"], 2);
+    assert!(value.get("version").is_none());
+
+    let json = transaction_decoder_22::run_with_options(SEGWIT_TRANSACTION_HEX.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["inputs"][0]["scriptSig"]["hex"], "");
+    assert_eq!(value["inputs"][0]["witness"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_witnesses_assigned_in_input_order() {
+    // input 0 spends a legacy output and has an empty witness,
+    // input 1 is a P2WPKH spend with a signature and public key
+    let raw_transaction_hex = "0200000000010242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffffd2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff018f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7f0002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef92800000000";
+    let transaction = transaction_decoder_22::decode(raw_transaction_hex.to_string()).unwrap();
+    assert_eq!(transaction.inputs.len(), 2);
+    assert!(transaction.inputs[0].witness.stack().is_empty());
+    assert_eq!(transaction.inputs[1].witness.stack().len(), 2);
+    assert_eq!(hex::encode(&transaction.inputs[1].witness.stack()[0]), "3044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101");
+    assert_eq!(hex::encode(&transaction.inputs[1].witness.stack()[1]), "0355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928");
+}
+
+#[test]
+fn test_nested_segwit() {
+    // the signed P2SH-P2WPKH example from BIP143
+    let raw_transaction_hex = "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000";
+    let transaction = transaction_decoder_22::decode(raw_transaction_hex.to_string()).unwrap();
+    assert!(transaction.inputs[0].is_nested_segwit());
+
+    let json = transaction_decoder_22::run(raw_transaction_hex.to_string()).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["inputs"][0]["nested_segwit"], true);
+
+    // native segwit and legacy spends aren't nested
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(!segwit.inputs[0].is_nested_segwit());
+    let legacy = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(!legacy.inputs[0].is_nested_segwit());
+}
+
+#[test]
+fn test_outpoints() {
+    let options = Options {
+        outpoints: true,
+        ..Default::default()
+    };
+    let output = transaction_decoder_22::run_with_options(LEGACY_TRANSACTION_HEX.to_string(), &options).unwrap();
+    assert_eq!(
+        output,
+        "8073cdf947ac97c23b77b055217da78d3ad71d30e1f6c095be8b30f7d6c1d542:1\n\
+         9cb414caf4a633b3446c22d6174be670b3e0e746024cc0c1ef0e15f3c57cc875:0"
+    );
+}
+
+#[test]
+fn test_serialized_len() {
+    let legacy = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(legacy.serialized_len(), legacy.to_bytes().len());
+    assert_eq!(legacy.base_size(), legacy.serialized_len());
+
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(segwit.serialized_len(), segwit.to_bytes().len());
+    assert_eq!(segwit.serialized_len(), SEGWIT_TRANSACTION_HEX.len() / 2);
+}
+
+#[test]
+fn test_clone_stripped() {
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    let stripped = segwit.clone_stripped();
+    assert!(segwit.is_segwit());
+    assert_eq!(segwit.inputs[0].witness.stack().len(), 2);
+    assert!(!stripped.is_segwit());
+    assert!(stripped.inputs[0].witness.stack().is_empty());
+
+    assert_eq!(stripped.txid(), segwit.txid());
+    assert_eq!(stripped.serialized_len(), segwit.base_size());
+}
+
+#[test]
+fn test_genesis_coinbase() {
+    let raw_transaction_hex = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+    let transaction = transaction_decoder_22::decode(raw_transaction_hex.to_string()).unwrap();
+    assert_eq!(transaction.txid().to_string(), "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+    assert!(transaction.inputs[0].is_coinbase());
+    assert_eq!(
+        transaction.inputs[0].coinbase_message().unwrap(),
+        "The Times 03/Jan/2009 Chancellor on brink of second bailout for banks"
+    );
+    assert_eq!(transaction.outputs[0].value_btc(), 50.0);
+
+    // only coinbase inputs have a coinbase message
+    let legacy = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(legacy.inputs[0].coinbase_message(), None);
+}
+
+#[test]
+fn test_address_to_script() {
+    let options = |address: &str| Options {
+        address_to_script: Some(address.to_string()),
+        ..Default::default()
+    };
+    // the raw transaction isn't needed
+    let p2wpkh = transaction_decoder_22::run_with_options(String::new(), &options("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")).unwrap();
+    assert_eq!(p2wpkh, "0014751e76e8199196d454941c45d1b3a323f1433bd6");
+
+    let p2pkh = transaction_decoder_22::run_with_options(String::new(), &options("18CZSmjX5tjq6hg5V3tX7m8sSERuqXHrWp")).unwrap();
+    assert_eq!(p2pkh, "76a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac");
+
+    let p2sh = transaction_decoder_22::run_with_options(String::new(), &options("3CWvc7XqLk5wZvJ9tPDkqQGNcSX8HCriLY")).unwrap();
+    assert_eq!(p2sh, "a91476c0c8f2fc403c5edaea365f6a284317b9cdf72587");
+
+    let p2tr = transaction_decoder_22::run_with_options(String::new(), &options("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0")).unwrap();
+    assert_eq!(p2tr, "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+
+    // a typo in the last character breaks the checksum
+    let err = transaction_decoder_22::run_with_options(String::new(), &options("18CZSmjX5tjq6hg5V3tX7m8sSERuqXHrWq")).unwrap_err();
+    assert_eq!(err.to_string(), "parse failed: invalid address checksum");
+    let err = transaction_decoder_22::run_with_options(String::new(), &options("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5")).unwrap_err();
+    assert_eq!(err.to_string(), "parse failed: invalid address checksum");
+}
+
+#[test]
+fn test_version_warning() {
+    let mut transaction = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    let value: serde_json::Value = serde_json::to_value(&transaction).unwrap();
+    assert!(value.get("warnings").is_none());
+
+    transaction.version.0 = 0xffffffff;
+    let value: serde_json::Value = serde_json::to_value(&transaction).unwrap();
+    assert_eq!(value["version"], 0xffffffff_u32);
+    assert_eq!(value["warnings"], serde_json::json!(["version -1 is negative"]));
+}
+
+#[test]
+fn test_large_transaction_decode_time() {
+    let raw_transaction_hex = hex::encode(common::large_transaction(20_000));
+
+    // decoding and rendering the JSON takes around half a second in a debug build
+    // and 50ms in a release build. Anything quadratic in the number of inputs
+    // would take thousands of times longer, so a generous budget still catches it.
+    let start = Instant::now();
+    let transaction = transaction_decoder_22::decode(raw_transaction_hex.clone()).unwrap();
+    let json = transaction_decoder_22::run(raw_transaction_hex.clone()).unwrap();
+    let elapsed = start.elapsed();
+
+    assert_eq!(transaction.inputs.len(), 20_000);
+    assert_eq!(transaction.serialized_len(), raw_transaction_hex.len() / 2);
+    assert!(json.contains("\"vsize\": 820043"));
+    assert!(elapsed < Duration::from_secs(10), "decoding took {:?}", elapsed);
+}
+
+#[test]
+fn test_explain() {
+    // the segwit transaction with its second output changed to pay a P2PKH address
+    let raw_transaction_hex = "02000000000101d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff028f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7fa9ce0f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac02473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928bff62400";
+    let options = Options {
+        explain: true,
+        ..Default::default()
+    };
+    let explanation = transaction_decoder_22::run_with_options(raw_transaction_hex.to_string(), &options).unwrap();
+    let expected = "\
+The first 4 bytes `02000000` are the version (2, little-endian).
+The next 2 bytes `0001` are the segwit marker and flag, so witnesses follow the outputs.
+The next byte `01` is the input count (1).
+Input 0 is the next 41 bytes, adding 271 weight units including its witness. It spends 0c0fe4cc11c477231ad80de3496e20f40cc3088797c50aec8996e955c87e46d2:1, as a native segwit spend with the signatures in the witness. Its last 4 bytes `feffffff` are the sequence, which read little-endian are 0xfffffffe or 4294967294 (non-final).
+The next byte `02` is the output count (2).
+Output 0 is the next 31 bytes, adding 124 weight units. This output pays 0.02034575 BTC to a P2WPKH address bc1qduzg6yup4f2x505faplh2j00c3032zmlfgnuvw (witness program for a single public key hash).
+Output 1 is the next 34 bytes, adding 136 weight units. This output pays 0.01035945 BTC to a P2PKH address 18CZSmjX5tjq6hg5V3tX7m8sSERuqXHrWp (hash of a single public key).
+The witness for input 0 is the next 107 bytes, holding 2 item(s).
+The last 4 bytes `bff62400` are the locktime, which read little-endian are 0x0024f6bf or 2422463 (a block height).
+Input 0 has a non-final sequence, which enables the locktime: the transaction can't be mined until block height 2422464.";
+    assert_eq!(explanation, expected);
+
+    // the derived address converts back to the output's scriptPubKey
+    let options = Options {
+        address_to_script: Some("bc1qduzg6yup4f2x505faplh2j00c3032zmlfgnuvw".to_string()),
+        ..Default::default()
+    };
+    let script_pubkey = transaction_decoder_22::run_with_options(String::new(), &options).unwrap();
+    assert_eq!(script_pubkey, "00146f048d1381aa546a3e89e87f7549efc45f150b7f");
+}
+
+#[test]
+fn test_explain_locktime_not_enforced() {
+    // the legacy transaction's inputs with final sequences and a timestamp locktime
+    let raw_transaction_hex = LEGACY_TRANSACTION_HEX
+        .replace("feffffff", "ffffffff")
+        .replace("8700000000", "8780d0ab65");
+    let options = Options {
+        explain: true,
+        ..Default::default()
+    };
+    let explanation = transaction_decoder_22::run_with_options(raw_transaction_hex, &options).unwrap();
+    assert!(explanation.contains(
+        "Its last 4 bytes `ffffffff` are the sequence, which read little-endian are 0xffffffff or 4294967295 (final)."
+    ));
+    assert!(explanation.ends_with("\
+The last 4 bytes `80d0ab65` are the locktime, which read little-endian are 0x65abd080 or 1705758848 (a unix timestamp).
+Every input's sequence is final (0xffffffff), so the locktime is not enforced and the transaction can be mined at any time."));
+
+    let raw_transaction_hex = LEGACY_TRANSACTION_HEX.replace("8700000000", "8780d0ab65");
+    let explanation = transaction_decoder_22::run_with_options(raw_transaction_hex, &options).unwrap();
+    assert!(explanation.ends_with("\
+Inputs 0, 1 have non-final sequences, which enables the locktime: the transaction can't be mined until the median time of the last 11 blocks is past 1705758848 (a unix timestamp)."));
+
+    // the walkthrough ends at the locktime, so bytes after it are rejected as in the other modes
+    let err = transaction_decoder_22::run_with_options(format!("{}01000000", LEGACY_TRANSACTION_HEX), &options).unwrap_err();
+    assert!(err.to_string().starts_with("4 trailing bytes after the transaction"));
+}
+
+#[test]
+fn test_unexpected_end_of_input() {
+    let err = transaction_decoder_22::decode("0100".to_string()).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input while reading version");
+
+    // cut off partway through the first input's scriptSig
+    let err = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX[..200].to_string()).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input while reading scriptSig");
+
+    // missing the last two bytes of the locktime
+    let truncated = &LEGACY_TRANSACTION_HEX[..LEGACY_TRANSACTION_HEX.len() - 4];
+    let err = transaction_decoder_22::decode(truncated.to_string()).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input while reading locktime");
+
+    let err = Transaction::decode_with_spans(&hex::decode(truncated).unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input while reading locktime");
+
+    // counts far bigger than the transaction are reported rather than allocated for
+    let err = Transaction::decode_with_spans(&hex::decode("01000000ffffffffffffffffff").unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input while reading previous output");
+    let raw_transaction_hex = format!("01000000000101{}0000000000fffffffffe00ffffff", "11".repeat(32));
+    let err = Transaction::decode_with_spans(&hex::decode(raw_transaction_hex).unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input while reading amount");
+}
+
+#[test]
+fn test_declared_input_count_too_high() {
+    // the legacy transaction's 2 inputs, with a count claiming 3, and nothing after them
+    let inputs_end = LEGACY_TRANSACTION_HEX.find("feffffff02ebb10f").unwrap() + 8;
+    let raw_transaction_hex = format!("0100000003{}", &LEGACY_TRANSACTION_HEX[10..inputs_end]);
+    let err = transaction_decoder_22::decode(raw_transaction_hex.clone()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parse failed: declared input count exceeds available inputs (declared 3, read 2)"
+    );
+
+    let err = Transaction::decode_with_spans(&hex::decode(raw_transaction_hex).unwrap()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "parse failed: declared input count exceeds available inputs (declared 3, read 2)"
+    );
+
+    // running out partway through the second input is an ordinary truncation
+    let truncated = &LEGACY_TRANSACTION_HEX[..500];
+    let err = transaction_decoder_22::decode(truncated.to_string()).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input while reading scriptSig");
+    let err = Transaction::decode_with_spans(&hex::decode(truncated).unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "unexpected end of input while reading scriptSig");
+}
+
+#[test]
+fn test_below_min_relay_size() {
+    // one input with an empty scriptSig and a single OP_RETURN output, 61 bytes
+    let raw_transaction_hex = format!("0200000001{}0000000000ffffffff010000000000000000016a00000000", "11".repeat(32));
+    let json: serde_json::Value = serde_json::from_str(&transaction_decoder_22::run(raw_transaction_hex).unwrap()).unwrap();
+    assert_eq!(json["size"], 61);
+    assert_eq!(json["warnings"], serde_json::json!(["the size without witnesses is below the 65 byte minimum for relay"]));
+
+    let json: serde_json::Value = serde_json::from_str(&transaction_decoder_22::run(LEGACY_TRANSACTION_HEX.to_string()).unwrap()).unwrap();
+    assert!(json.get("warnings").is_none());
+}
+
+#[test]
+fn test_assume_legacy() {
+    // no inputs and a single zero value OP_RETURN output. The 00 01 after the version
+    // also reads as the segwit marker and flag, so by default this decodes as a segwit
+    // transaction with no inputs or outputs and the locktime taken from the amount
+    let raw_transaction_hex = "0100000000010000000000000000016a00000000";
+    let default = transaction_decoder_22::decode(raw_transaction_hex.to_string()).unwrap();
+    assert!(default.outputs.is_empty());
+
+    let legacy = transaction_decoder_22::decode_legacy(raw_transaction_hex.to_string()).unwrap();
+    assert!(legacy.inputs.is_empty());
+    assert_eq!(legacy.outputs.len(), 1);
+    assert_eq!(legacy.outputs[0].script_pubkey, "6a");
+
+    // the segwit reading leaves 8 bytes after its locktime, which run rejects
+    let error = transaction_decoder_22::run(raw_transaction_hex.to_string()).unwrap_err();
+    assert_eq!(error.to_string(), "8 trailing bytes after the transaction at offset 12");
+    let options = Options { assume_legacy: true, ..Default::default() };
+    let json: serde_json::Value = serde_json::from_str(
+        &transaction_decoder_22::run_with_options(raw_transaction_hex.to_string(), &options).unwrap()
+    ).unwrap();
+    assert_eq!(json["outputs"][0]["script_pubkey"], "6a");
+
+    // trailing bytes after a legacy transaction are rejected unless tolerated
+    let with_trailing = format!("{}01000000", raw_transaction_hex);
+    let error = transaction_decoder_22::run_with_options(with_trailing.clone(), &options).unwrap_err();
+    assert_eq!(error.to_string(), "4 trailing bytes after the transaction at offset 20");
+    let options = Options { assume_legacy: true, tolerate_trailing: true, ..Default::default() };
+    let json: serde_json::Value = serde_json::from_str(
+        &transaction_decoder_22::run_with_options(with_trailing, &options).unwrap()
+    ).unwrap();
+    assert_eq!(json["trailing_bytes"], serde_json::json!({ "count": 4, "hex": "01000000" }));
+}
+
+#[test]
+fn test_witness_commitment() {
+    // a segwit coinbase laid out the way miners build them, with a made up commitment:
+    // the height and a tag in the scriptSig, a payout, the OP_RETURN commitment output
+    // and a witness holding the 32 byte witness reserved value
+    let raw_transaction_hex = "010000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff1103a0bb0d0c2f74657374206d696e65722fffffffff028e1ffd2500000000160014751e76e8199196d454941c45d1b3a323f1433bd60000000000000000266a24aa21a9edd4507454ba1ddf94015831f878bf4260d1581e9148516f05accddaf4bd4d9c6f0120000000000000000000000000000000000000000000000000000000000000000000000000";
+    let coinbase = transaction_decoder_22::decode(raw_transaction_hex.to_string()).unwrap();
+    assert!(coinbase.is_coinbase());
+    assert_eq!(coinbase.inputs[0].witness.items(), &[vec![0; 32]]);
+    assert_eq!(
+        hex::encode(coinbase.witness_commitment().unwrap()),
+        "d4507454ba1ddf94015831f878bf4260d1581e9148516f05accddaf4bd4d9c6f"
+    );
+
+    // only a coinbase has a witness commitment
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(!segwit.is_coinbase());
+    assert_eq!(segwit.witness_commitment(), None);
+}
+
+#[test]
+fn test_coinbase_witness_valid() {
+    // the coinbase from test_witness_commitment, whose witness is the 32 byte reserved value
+    let raw_transaction_hex = "010000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff1103a0bb0d0c2f74657374206d696e65722fffffffff028e1ffd2500000000160014751e76e8199196d454941c45d1b3a323f1433bd60000000000000000266a24aa21a9edd4507454ba1ddf94015831f878bf4260d1581e9148516f05accddaf4bd4d9c6f0120000000000000000000000000000000000000000000000000000000000000000000000000";
+    let coinbase = transaction_decoder_22::decode(raw_transaction_hex.to_string()).unwrap();
+    assert_eq!(coinbase.coinbase_witness_valid(), Some(true));
+    let json: serde_json::Value = serde_json::from_str(&transaction_decoder_22::run(raw_transaction_hex.to_string()).unwrap()).unwrap();
+    assert_eq!(json["coinbase_witness_valid"], true);
+
+    // a second witness item after the reserved value
+    let witness = format!("0120{}00000000", "00".repeat(32));
+    let two_items = raw_transaction_hex.replace(&witness, &format!("0220{}01ab00000000", "00".repeat(32)));
+    let coinbase = transaction_decoder_22::decode(two_items.clone()).unwrap();
+    assert_eq!(coinbase.coinbase_witness_valid(), Some(false));
+    let json: serde_json::Value = serde_json::from_str(&transaction_decoder_22::run(two_items).unwrap()).unwrap();
+    assert_eq!(json["coinbase_witness_valid"], false);
+
+    // a single item that's too short
+    let short_item = raw_transaction_hex.replace(&witness, &format!("011f{}00000000", "00".repeat(31)));
+    let coinbase = transaction_decoder_22::decode(short_item).unwrap();
+    assert_eq!(coinbase.coinbase_witness_valid(), Some(false));
+
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(segwit.coinbase_witness_valid(), None);
+    let json: serde_json::Value = serde_json::from_str(&transaction_decoder_22::run(SEGWIT_TRANSACTION_HEX.to_string()).unwrap()).unwrap();
+    assert!(json.get("coinbase_witness_valid").is_none());
+}
+
+#[cfg(feature = "resolve-time")]
+#[test]
+fn test_resolve_time() {
+    let options = Options {
+        resolve_time: true,
+        ..Default::default()
+    };
+    // a locktime of 1700000000 is a unix timestamp
+    let timestamp_locktime_hex = format!("{}00f15365", &LEGACY_TRANSACTION_HEX[..LEGACY_TRANSACTION_HEX.len() - 8]);
+    let json = transaction_decoder_22::run_with_options(timestamp_locktime_hex, &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["locktime"], 1_700_000_000);
+    assert_eq!(value["locktime_date"], "2023-11-14T22:13:20Z");
+
+    // the segwit transaction's locktime is a block height
+    let json = transaction_decoder_22::run_with_options(SEGWIT_TRANSACTION_HEX.to_string(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["locktime"], 2_422_463);
+    assert!(value.get("locktime_date").is_none());
+}
+
+#[test]
+fn test_key_style() {
+    let keys = |hex: &str, key_style| {
+        let options = Options { key_style, ..Default::default() };
+        let json = transaction_decoder_22::run_with_options(hex.to_string(), &options).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let object_keys = |value: &serde_json::Value| value.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        [object_keys(&value), object_keys(&value["inputs"][0]), object_keys(&value["outputs"][0])].concat()
+    };
+
+    let mixed = keys(SEGWIT_TRANSACTION_HEX, KeyStyle::Mixed);
+    for key in ["locktime", "vout", "txinwitness", "script_pubkey"] {
+        assert!(mixed.contains(&key.to_string()), "{}", key);
+    }
+
+    let core = [keys(LEGACY_TRANSACTION_HEX, KeyStyle::Core), keys(SEGWIT_TRANSACTION_HEX, KeyStyle::Core)].concat();
+    for key in ["scriptSig", "scriptPubKey", "txinwitness", "locktime", "vout"] {
+        assert!(core.contains(&key.to_string()), "{}", key);
+    }
+    for key in ["script_sig", "script_pubkey", "witness", "lock_time", "output_index"] {
+        assert!(!core.contains(&key.to_string()), "{}", key);
+    }
+
+    let snake = [keys(LEGACY_TRANSACTION_HEX, KeyStyle::Snake), keys(SEGWIT_TRANSACTION_HEX, KeyStyle::Snake)].concat();
+    for key in ["script_sig", "script_pubkey", "witness", "lock_time", "output_index"] {
+        assert!(snake.contains(&key.to_string()), "{}", key);
+    }
+    for key in ["scriptSig", "scriptPubKey", "txinwitness", "locktime", "vout"] {
+        assert!(!snake.contains(&key.to_string()), "{}", key);
+    }
+}
+
+#[test]
+fn test_component_weights() {
+    // input 0 has an empty witness, input 1 is a P2WPKH spend
+    let mixed_transaction_hex = "0200000000010242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffffd2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff018f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7f0002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef92800000000";
+    for raw_transaction_hex in [LEGACY_TRANSACTION_HEX, SEGWIT_TRANSACTION_HEX, mixed_transaction_hex] {
+        let transaction = transaction_decoder_22::decode(raw_transaction_hex.to_string()).unwrap();
+        let inputs: usize = transaction.input_weights().iter().sum();
+        let outputs: usize = transaction.outputs.iter().map(|output| output.weight()).sum();
+
+        // the version, counts and locktime, plus the marker and flag for a segwit transaction
+        let counts = CompactSize(transaction.inputs.len() as u64).serialized_len()
+            + CompactSize(transaction.outputs.len() as u64).serialized_len();
+        let marker_and_flag = if transaction.is_segwit() { 2 } else { 0 };
+        let fixed = (4 + counts + 4) * 4 + marker_and_flag;
+        assert_eq!(inputs + outputs + fixed, transaction.weight());
+    }
+}
+
+#[test]
+fn test_output_addresses() {
+    // a made up input paying a P2WPKH output and an OP_RETURN output
+    let raw_transaction_hex = format!(
+        "0100000001{}0000000000ffffffff02{}1600146f048d1381aa546a3e89e87f7549efc45f150b7f{}026a0000000000",
+        "11".repeat(32), "8f0b1f0000000000", "0000000000000000"
+    );
+    let transaction = transaction_decoder_22::decode(raw_transaction_hex).unwrap();
+    assert_eq!(
+        transaction.output_addresses(Network::Mainnet),
+        vec![Some("bc1qduzg6yup4f2x505faplh2j00c3032zmlfgnuvw".to_string()), None]
+    );
+    assert_eq!(
+        transaction.output_addresses(Network::Testnet),
+        vec![Some("tb1qduzg6yup4f2x505faplh2j00c3032zmlrwg0ha".to_string()), None]
+    );
+
+    // the legacy transaction pays a P2PKH and a P2SH address
+    let transaction = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(
+        transaction.output_addresses(Network::Testnet),
+        vec![
+            Some("mniWjppVtvB5sp9hCcrtwgMCJE2cngUggc".to_string()),
+            Some("2N458frTrxCbHmhvhZWqdTMFdpnjJ3Tt68F".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_outputs_classified() {
+    // the segwit transaction's two P2WPKH outputs followed by a P2PKH
+    // output and another P2WPKH output
+    let raw_transaction_hex = SEGWIT_TRANSACTION_HEX.replacen("028f0b1f", "048f0b1f", 1).replacen(
+        "7f4002473044",
+        &format!(
+            "7f40{}1976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac{}1600146f048d1381aa546a3e89e87f7549efc45f150b7f02473044",
+            "ebb10f0000000000", "0100000000000000"
+        ),
+        1,
+    );
+    let transaction = transaction_decoder_22::decode(raw_transaction_hex).unwrap();
+    let types: Vec<ScriptType> = transaction.outputs_classified().map(|(_, _, script_type)| script_type).collect();
+    assert_eq!(types, vec![ScriptType::P2wpkh, ScriptType::P2wpkh, ScriptType::P2pkh, ScriptType::P2wpkh]);
+
+    let p2wpkh: Vec<(usize, Amount)> = transaction.outputs_classified()
+        .filter(|(_, _, script_type)| *script_type == ScriptType::P2wpkh)
+        .map(|(i, output, _)| (i, output.amount))
+        .collect();
+    assert_eq!(
+        p2wpkh,
+        vec![(0, Amount::from_sat(2_034_575)), (1, Amount::from_sat(1_035_945)), (3, Amount::from_sat(1))]
+    );
+}
+
+#[test]
+fn test_decode_strict() {
+    // the segwit transaction with its flag changed from 0x01 to 0x03
+    let raw_transaction_hex = SEGWIT_TRANSACTION_HEX.replacen("020000000001", "020000000003", 1);
+    let err = transaction_decoder_22::decode_strict(raw_transaction_hex).unwrap_err();
+    match err.downcast_ref::<transaction_decoder_22::transaction::Error>() {
+        Some(transaction_decoder_22::transaction::Error::UnsupportedSegwitFlag { flag, offset, inputs, outputs }) => {
+            assert_eq!(*flag, 0x03);
+            assert_eq!(*offset, 5);
+            assert_eq!(*inputs, Some(1));
+            assert_eq!(*outputs, Some(2));
+        }
+        other => panic!("expected an unsupported segwit flag error, got {:?}", other),
+    }
+    assert_eq!(err.to_string(), "unsupported segwit flag 0x03 at offset 5 (followed by 1 inputs and 2 outputs)");
+
+    // decode stops at the flag without reading on
+    let err = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.replacen("020000000001", "020000000003", 1)).unwrap_err();
+    assert_eq!(err.to_string(), "unsupported segwit flag 0x03 at offset 5");
+
+    // nothing readable after the flag
+    let err = transaction_decoder_22::decode_strict("020000000003".to_string()).unwrap_err();
+    assert_eq!(err.to_string(), "unsupported segwit flag 0x03 at offset 5");
+
+    // decode ignores anything after the locktime but decode_strict doesn't
+    let raw_transaction_hex = format!("{}0000", SEGWIT_TRANSACTION_HEX);
+    assert!(transaction_decoder_22::decode(raw_transaction_hex.clone()).is_ok());
+    let err = transaction_decoder_22::decode_strict(raw_transaction_hex).unwrap_err();
+    assert_eq!(err.to_string(), "2 trailing bytes after the transaction at offset 222");
+    assert!(transaction_decoder_22::decode_strict(SEGWIT_TRANSACTION_HEX.to_string()).is_ok());
+}
+
+#[test]
+fn test_transform_outputs() {
+    // a made up input and two P2WPKH outputs of 1 and 1,500,000 satoshis
+    let raw_transaction_hex = format!(
+        "0100000001{}ffffffff00ffffffff02{}160014{}{}160014{}00000000",
+        "11".repeat(32), "0100000000000000", "22".repeat(20), "60e3160000000000", "33".repeat(20)
+    );
+    let mut transaction = transaction_decoder_22::decode(raw_transaction_hex).unwrap();
+    transaction.retain_outputs(|output| !output.is_dust(DEFAULT_DUST_RELAY_FEE));
+    transaction.map_output_amounts(|amount| Amount::from_sat(amount.to_sat() - 1_000));
+
+    let reencoded = transaction_decoder_22::decode(hex::encode(transaction.to_bytes())).unwrap();
+    assert_eq!(reencoded.outputs.len(), 1);
+    assert_eq!(reencoded.outputs[0].amount, Amount::from_sat(1_499_000));
+    assert_eq!(reencoded.outputs[0].script_pubkey, format!("0014{}", "33".repeat(20)));
+}
+
+#[test]
+fn test_input_types_uniform() {
+    let segwit = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(segwit.input_types_uniform());
+    assert_eq!(segwit.input_types().into_iter().collect::<Vec<_>>(), vec![SpendType::NativeSegwit]);
+
+    let legacy = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(legacy.input_types_uniform());
+
+    // input 0 spends a legacy output, input 1 is a P2WPKH spend
+    let mixed = transaction_decoder_22::decode("0200000000010242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffffd2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff018f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7f0002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef92800000000".to_string()).unwrap();
+    assert!(!mixed.input_types_uniform());
+    assert_eq!(mixed.input_types().into_iter().collect::<Vec<_>>(), vec![SpendType::Legacy, SpendType::NativeSegwit]);
+}
+
+#[test]
+fn test_tolerate_trailing() {
+    // a sighash type copied along with the transaction
+    let raw_transaction_hex = format!("{}01000000", SEGWIT_TRANSACTION_HEX);
+    let options = Options { tolerate_trailing: true, ..Default::default() };
+    let json = transaction_decoder_22::run_with_options(raw_transaction_hex.clone(), &options).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["trailing_bytes"], serde_json::json!({ "count": 4, "hex": "01000000" }));
+    assert_eq!(value["transaction_id"], "17e1fcaae34575d0d1566c1ae64bf4c9f7b7b9df0ff505015d3eb72460fe3a61");
+
+    // nothing to report without trailing bytes, and rejected without the option
+    let json = transaction_decoder_22::run_with_options(SEGWIT_TRANSACTION_HEX.to_string(), &options).unwrap();
+    assert!(!json.contains("trailing_bytes"));
+    let error = transaction_decoder_22::run(raw_transaction_hex).unwrap_err();
+    assert!(error.to_string().starts_with("4 trailing bytes after the transaction"));
+}
+
+#[test]
+fn test_duplicate_inputs() {
+    let transaction = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(!transaction.has_duplicate_inputs());
+    assert!(!transaction_decoder_22::run(LEGACY_TRANSACTION_HEX.to_string()).unwrap().contains("\"warnings\""));
+
+    // the second input changed to spend the same outpoint as the first
+    let first = "42d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd738001000000";
+    let second = "75c87cc5f3150eefc1c04c0246e7e0b370e64b17d6226c44b333a6f4ca14b49c00000000";
+    let raw_transaction_hex = LEGACY_TRANSACTION_HEX.replacen(second, first, 1);
+    let transaction = transaction_decoder_22::decode(raw_transaction_hex.clone()).unwrap();
+    assert!(transaction.has_duplicate_inputs());
+    let json = transaction_decoder_22::run(raw_transaction_hex).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["warnings"], serde_json::json!(["spends the same outpoint more than once"]));
+
+    // the same txid with a different vout is a different outpoint
+    let raw_transaction_hex = LEGACY_TRANSACTION_HEX.replacen(second, &first.replacen("01000000", "00000000", 1), 1);
+    assert!(!transaction_decoder_22::decode(raw_transaction_hex).unwrap().has_duplicate_inputs());
+}
+
+#[test]
+fn test_amount_units() {
+    let json = |amount_unit| {
+        let options = Options { amount_unit, ..Default::default() };
+        let json = transaction_decoder_22::run_with_options(SEGWIT_TRANSACTION_HEX.to_string(), &options).unwrap();
+        serde_json::from_str::<serde_json::Value>(&json).unwrap()
+    };
+    assert_eq!(json(AmountUnit::Btc)["outputs"][0]["amount"], 0.02034575);
+    assert_eq!(json(AmountUnit::Sat)["outputs"][0]["amount"], 2_034_575);
+    assert_eq!(
+        json(AmountUnit::Both)["outputs"][1]["amount"],
+        serde_json::json!({ "sat": 1_035_945, "btc": "0.01035945" })
+    );
+}
+
+#[test]
+fn test_segwit_versions_used() {
+    let mut transaction = transaction_decoder_22::decode(SEGWIT_TRANSACTION_HEX.to_string()).unwrap();
+    assert_eq!(transaction.segwit_versions_used().into_iter().collect::<Vec<_>>(), [0]);
+
+    // the second output changed to pay to a taproot output key
+    transaction.outputs[1].script_pubkey = "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c".to_string();
+    assert_eq!(transaction.segwit_versions_used().into_iter().collect::<Vec<_>>(), [0, 1]);
+
+    let transaction = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(transaction.segwit_versions_used().is_empty());
+}
+
+#[test]
+fn test_warnings() {
+    let transaction = transaction_decoder_22::decode(LEGACY_TRANSACTION_HEX.to_string()).unwrap();
+    assert!(transaction.warnings().is_empty());
+    assert!(!transaction_decoder_22::run(LEGACY_TRANSACTION_HEX.to_string()).unwrap().contains("\"warnings\""));
+
+    // a negative version, the first input spent twice with a non-minimal push of
+    // the byte 2 as its scriptSig, and an output worth more than all bitcoin
+    let mut crafted = transaction.clone();
+    crafted.version.0 = 0xffffffff;
+    crafted.inputs[0].script_sig = "0102".to_string();
+    crafted.inputs[1] = crafted.inputs[0].clone();
+    crafted.outputs[0].amount = Amount::from_sat(21_000_000 * 100_000_000 + 1);
+    assert_eq!(
+        crafted.warnings(),
+        [
+            Warning::NonStandardVersion(-1),
+            Warning::DuplicateInputs,
+            Warning::NonMinimalPush { input: 0 },
+            Warning::NonMinimalPush { input: 1 },
+            Warning::AmountsExceedSupply,
+        ]
+    );
+    let json = serde_json::to_value(&crafted).unwrap();
+    assert_eq!(json["warnings"][0], "version -1 is negative");
+    assert_eq!(json["warnings"][2], "input 0 has a scriptSig push that isn't minimally encoded");
+
+    // a scriptSig that isn't hex can't be checked for minimal pushes
+    let mut invalid_hex = transaction.clone();
+    invalid_hex.inputs[0].script_sig = "01zz".to_string();
+    assert_eq!(invalid_hex.inputs[0].has_minimal_pushes(), None);
+    assert!(invalid_hex.warnings().is_empty());
+    assert!(!serde_json::to_string(&invalid_hex.inputs[0]).unwrap().contains("non_minimal_push"));
+
+    // a tiny coinbase with an extra witness item. Its scriptSig isn't checked for
+    // minimal pushes since it's never executed.
+    let mut coinbase = transaction.clone();
+    coinbase.inputs.truncate(1);
+    coinbase.inputs[0].previous_output = OutPoint { txid: Txid([0; 32]), vout: 0xffffffff };
+    coinbase.inputs[0].script_sig = "0102".to_string();
+    coinbase.inputs[0].witness = Witness::from_items(vec![vec![0; 32], vec![0xab]]);
+    coinbase.outputs.truncate(1);
+    coinbase.outputs[0].script_pubkey = "6a".to_string();
+    assert_eq!(coinbase.warnings(), [Warning::InvalidCoinbaseWitness, Warning::BelowMinRelaySize]);
+}
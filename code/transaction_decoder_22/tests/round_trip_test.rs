@@ -0,0 +1,64 @@
+use transaction_decoder_22::prelude::*;
+
+// Raw transactions covering each layout the decoder handles, for checking that
+// decoding and encoding are exact inverses of each other
+const CORPUS: [(&str, &str); 7] = [
+    // legacy P2PKH spends, the course's running example
+    // https://mempool.space/testnet/tx/3c1804567a336c3944e30b3c2593970bfcbf5b15a40f4fc6b626a360ee0507f2
+    ("legacy", "010000000242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffff75c87cc5f3150eefc1c04c0246e7e0b370e64b17d6226c44b333a6f4ca14b49c000000006b483045022100e0d85fece671d367c8d442a96230954cdda4b9cf95e9edc763616d05d93e944302202330d520408d909575c5f6976cc405b3042673b601f4f2140b2e4d447e671c47012103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abffeffffff02ebb10f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac508c1e000000000017a91476c0c8f2fc403c5edaea365f6a284317b9cdf7258700000000"),
+    // a P2WPKH spend
+    // https://mempool.space/testnet/tx/17e1fcaae34575d0d1566c1ae64bf4c9f7b7b9df0ff505015d3eb72460fe3a61
+    ("segwit", "02000000000101d2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff028f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7fa9ce0f0000000000160014d850c02b89821f0f189ca7e81756c102241f7f4002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef928bff62400"),
+    // the first transaction between two people, paying to a public key in block 170
+    // https://mempool.space/tx/f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16
+    ("p2pk", "0100000001c997a5e56e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd3704000000004847304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901ffffffff0200ca9a3b00000000434104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000"),
+    // the genesis block's coinbase
+    // https://mempool.space/tx/4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b
+    ("coinbase", "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"),
+    // the signed P2SH-P2WPKH example from BIP143, which was never broadcast
+    // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki
+    ("nested segwit", "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000"),
+    // made up: a 2-of-3 P2SH multisig spend with a 252 byte scriptSig, the largest
+    // whose length still fits in a single byte
+    ("p2sh multisig", "0100000001010101010101010101010101010101010101010101010101010101010101010100000000fc00473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f581533561014c6952210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef92821030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd52103c43afccd37aae7107f5a43f5b7b223d034e7583b77c8cd1084d86895a7341abf53aeffffffff0140420f00000000001976a9144ef88a0b04e3ad6d1888da4be260d6735e0d308488ac00000000"),
+    // made up: a legacy input with an empty witness followed by a P2WPKH input
+    ("mixed witnesses", "0200000000010242d5c1d6f7308bbe95c0f6e1301dd73a8da77d2155b0773bc297ac47f9cd7380010000006a4730440220771361aae55e84496b9e7b06e0a53dd122a1425f85840af7a52b20fa329816070220221dd92132e82ef9c133cb1a106b64893892a11acf2cfa1adb7698dcdc02f01b0121030077be25dc482e7f4abad60115416881fe4ef98af33c924cd8b20ca4e57e8bd5feffffffd2467ec855e99689ec0ac5978708c30cf4206e49e30dd81a2377c411cce40f0c0100000000feffffff018f0b1f00000000001600146f048d1381aa546a3e89e87f7549efc45f150b7f0002473044022036c03ad8796f865c9348403fb705d5b984a4ef9565e8b0c81a1069f0f36bbeeb022034e9d5679e9783a441586fae034c78c60854ed71b7b53e6ef169e4f58153356101210355dd8af3cbfe5c3d3424b441069455a59ce0c8d5fe628da0913dae55037ef92800000000"),
+];
+
+fn decode(bytes: &[u8]) -> Transaction {
+    Transaction::consensus_decode(&mut &bytes[..]).unwrap()
+}
+
+fn encode(transaction: &Transaction) -> Vec<u8> {
+    let mut bytes = vec![];
+    transaction.consensus_encode(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn test_encode_inverts_decode() {
+    for (name, raw_transaction_hex) in CORPUS {
+        let bytes = hex::decode(raw_transaction_hex).unwrap();
+        assert_eq!(hex::encode(encode(&decode(&bytes))), raw_transaction_hex, "{}", name);
+    }
+}
+
+#[test]
+fn test_decode_is_idempotent() {
+    for (name, raw_transaction_hex) in CORPUS {
+        let decoded = decode(&hex::decode(raw_transaction_hex).unwrap());
+        assert_eq!(decode(&encode(&decoded)), decoded, "{}", name);
+    }
+}
+
+#[test]
+fn test_ids_survive_round_trip() {
+    for (name, raw_transaction_hex) in CORPUS {
+        let decoded = decode(&hex::decode(raw_transaction_hex).unwrap());
+        let redecoded = decode(&encode(&decoded));
+        assert_eq!(redecoded.txid(), decoded.txid(), "{}", name);
+        assert_eq!(redecoded.wtxid(), decoded.wtxid(), "{}", name);
+        // a transaction without witnesses has the same txid and wtxid
+        assert_eq!(decoded.txid() == decoded.wtxid(), !decoded.is_segwit(), "{}", name);
+    }
+}